@@ -0,0 +1,532 @@
+// fraction of the step size the response has to stay within to be considered settled
+const SETTLING_BAND: f64 = 0.02;
+// fraction of the window at the end used to estimate the steady state error
+const STEADY_STATE_FRACTION: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct StepResponse {
+    pub start: f64,
+    pub initial: f64,
+    pub setpoint: f64,
+    // times at which the response first crossed 10% and 90% of the step
+    pub rise_start: Option<f64>,
+    pub rise_end: Option<f64>,
+    pub peak: [f64; 2],
+    pub overshoot: f64,
+    pub settled_at: Option<f64>,
+    pub steady_state_error: f64,
+    pub rms_error: f64,
+    pub peak_to_peak: f64,
+}
+
+impl StepResponse {
+    // analyse the measurement within [start, end] against the setpoint
+    // (the setpoint is treated as a sample and hold signal)
+    pub fn analyse(
+        setpoint: &[[f64; 2]],
+        measurement: &[[f64; 2]],
+        start: f64,
+        end: f64,
+    ) -> Option<Self> {
+        let samples: Vec<([f64; 2], f64)> = measurement
+            .iter()
+            .filter(|p| p[0] >= start && p[0] <= end)
+            .filter_map(|&p| Some((p, Self::sample_and_hold(setpoint, p[0])?)))
+            .collect();
+        if samples.len() < 2 {
+            return None;
+        }
+
+        let initial = samples[0].0[1];
+        let target = samples[samples.len() - 1].1;
+        let step = target - initial;
+        // direction of the step so overshoot and crossings work for negative steps
+        let dir = if step < 0.0 { -1.0 } else { 1.0 };
+
+        let crossing = |frac: f64| {
+            samples
+                .iter()
+                .find(|(p, _)| dir * (p[1] - initial) >= frac * step.abs())
+                .map(|(p, _)| p[0])
+        };
+        let (rise_start, rise_end) = if step == 0.0 {
+            (None, None)
+        } else {
+            (crossing(0.1), crossing(0.9))
+        };
+
+        let peak = samples
+            .iter()
+            .map(|(p, _)| *p)
+            .max_by(|a, b| (dir * a[1]).total_cmp(&(dir * b[1])))
+            .unwrap();
+        let overshoot = if step == 0.0 {
+            0.0
+        } else {
+            (dir * (peak[1] - target) / step.abs() * 100.0).max(0.0)
+        };
+
+        // settled once the error stays within the band until the end of the window
+        let band = SETTLING_BAND * step.abs();
+        let settled_idx = match samples.iter().rposition(|(p, sp)| (sp - p[1]).abs() > band) {
+            None => Some(0),
+            Some(i) if i + 1 < samples.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        let settled_at = settled_idx.map(|i| samples[i].0[0]);
+
+        let steady_len = ((samples.len() as f64 * STEADY_STATE_FRACTION).ceil() as usize).max(1);
+        let steady_state_error = samples[samples.len() - steady_len..]
+            .iter()
+            .map(|(p, sp)| sp - p[1])
+            .sum::<f64>()
+            / steady_len as f64;
+
+        let rms_error = (samples
+            .iter()
+            .map(|(p, sp)| (sp - p[1]).powi(2))
+            .sum::<f64>()
+            / samples.len() as f64)
+            .sqrt();
+
+        // oscillation is measured after settling, or over the final quarter if never settled
+        let tail_start = settled_idx.unwrap_or(samples.len() - samples.len() / 4 - 1);
+        let (min, max) = samples[tail_start..]
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (p, _)| {
+                (min.min(p[1]), max.max(p[1]))
+            });
+
+        Some(Self {
+            start: samples[0].0[0],
+            initial,
+            setpoint: target,
+            rise_start,
+            rise_end,
+            peak,
+            overshoot,
+            settled_at,
+            steady_state_error,
+            rms_error,
+            peak_to_peak: max - min,
+        })
+    }
+    pub fn rise_time(&self) -> Option<f64> {
+        Some(self.rise_end? - self.rise_start?)
+    }
+    pub fn settling_time(&self) -> Option<f64> {
+        self.settled_at.map(|t| t - self.start)
+    }
+    pub fn settling_band(&self) -> f64 {
+        SETTLING_BAND * (self.setpoint - self.initial).abs()
+    }
+    fn sample_and_hold(points: &[[f64; 2]], t: f64) -> Option<f64> {
+        // points are sorted by time so find the last point at or before t
+        let idx = points.partition_point(|p| p[0] <= t);
+        if idx == 0 {
+            points.first().map(|p| p[1])
+        } else {
+            Some(points[idx - 1][1])
+        }
+    }
+}
+
+// limit cycle of the measurement during a relay feedback experiment
+#[derive(Debug, Clone, Copy)]
+pub struct Oscillation {
+    pub period: f64,
+    // half the peak to peak swing of the measurement
+    pub amplitude: f64,
+}
+
+impl Oscillation {
+    // measure the last `cycles` complete cycles of the samples, returns none
+    // until that many have been seen
+    pub fn analyse(samples: &[[f64; 2]], cycles: usize) -> Option<Self> {
+        // the start is usually a transient so the centre and swing are
+        // estimated from the second half
+        let settled = &samples[samples.len() / 2..];
+        if settled.len() < 2 || cycles == 0 {
+            return None;
+        }
+        let mid = settled.iter().map(|p| p[1]).sum::<f64>() / settled.len() as f64;
+        let (lo, hi) = Self::extent(settled);
+        // crossings only count after dipping below this, ignoring noise around the centre
+        let band = 0.1 * (hi - lo) / 2.0;
+
+        let mut crossings = Vec::new();
+        let mut armed = false;
+        for w in samples.windows(2) {
+            let ([t0, y0], [t1, y1]) = (w[0], w[1]);
+            if y1 < mid - band {
+                armed = true;
+            } else if armed && y0 < mid && y1 >= mid {
+                crossings.push(t0 + (mid - y0) / (y1 - y0) * (t1 - t0));
+                armed = false;
+            }
+        }
+        if crossings.len() <= cycles {
+            return None;
+        }
+
+        let recent = &crossings[crossings.len() - cycles - 1..];
+        let (start, end) = (recent[0], *recent.last().unwrap());
+        let window: Vec<[f64; 2]> = samples
+            .iter()
+            .filter(|p| p[0] >= start && p[0] <= end)
+            .copied()
+            .collect();
+        let (lo, hi) = Self::extent(&window);
+        Some(Self {
+            period: (end - start) / cycles as f64,
+            amplitude: (hi - lo) / 2.0,
+        })
+    }
+    fn extent(samples: &[[f64; 2]]) -> (f64, f64) {
+        samples
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+                (lo.min(p[1]), hi.max(p[1]))
+            })
+    }
+    // describing function approximation for an ideal relay switching between
+    // +-relay around the setpoint
+    pub fn ultimate_gain(&self, relay: f64) -> f64 {
+        4.0 * relay / (std::f64::consts::PI * self.amplitude)
+    }
+}
+
+// drivetrain feed-forward model: voltage = ks * sign(v) + kv * v + ka * a
+#[derive(Debug, Clone)]
+pub struct FeedForward {
+    pub ks: f64,
+    pub kv: f64,
+    pub ka: f64,
+    pub r_squared: f64,
+    // time, velocity and residual of every sample used in the fit
+    pub residuals: Vec<[f64; 3]>,
+}
+
+impl FeedForward {
+    // least squares fit at the voltage sample times, velocity and acceleration are
+    // interpolated and samples slower than `min_velocity` are skipped as the
+    // mechanism is likely stuck in static friction
+    pub fn fit(
+        voltage: &[[f64; 2]],
+        velocity: &[[f64; 2]],
+        acceleration: &[[f64; 2]],
+        min_velocity: f64,
+    ) -> Option<Self> {
+        let samples: Vec<(f64, [f64; 3], f64)> = voltage
+            .iter()
+            .filter_map(|&[t, volts]| {
+                let v = Self::interpolate(velocity, t)?;
+                let a = Self::interpolate(acceleration, t)?;
+                // signum is 1 at zero, a robot at rest has no friction to overcome
+                let sign = if v == 0.0 { 0.0 } else { v.signum() };
+                (v.abs() >= min_velocity).then_some((t, [sign, v, a], volts))
+            })
+            .collect();
+        if samples.len() < 3 {
+            return None;
+        }
+
+        // normal equations (X^T X) k = X^T y
+        let mut xtx = [[0.0; 3]; 3];
+        let mut xty = [0.0; 3];
+        for (_, x, y) in &samples {
+            for ((row, acc), xi) in xtx.iter_mut().zip(&mut xty).zip(x) {
+                for (cell, xj) in row.iter_mut().zip(x) {
+                    *cell += xi * xj;
+                }
+                *acc += xi * y;
+            }
+        }
+        let [ks, kv, ka] = Self::solve(xtx, xty)?;
+
+        let residuals: Vec<[f64; 3]> = samples
+            .iter()
+            .map(|(t, [sign, v, a], y)| [*t, *v, y - (ks * sign + kv * v + ka * a)])
+            .collect();
+        let mean = samples.iter().map(|(_, _, y)| y).sum::<f64>() / samples.len() as f64;
+        let ss_tot: f64 = samples.iter().map(|(_, _, y)| (y - mean).powi(2)).sum();
+        let ss_res: f64 = residuals.iter().map(|r| r[2].powi(2)).sum();
+        // with no variation in the voltage there is nothing to explain, so only
+        // an exact fit counts
+        let r_squared = if ss_tot > 0.0 {
+            1.0 - ss_res / ss_tot
+        } else if ss_res <= 1e-12 * samples.len() as f64 {
+            1.0
+        } else {
+            0.0
+        };
+        Some(Self {
+            ks,
+            kv,
+            ka,
+            r_squared,
+            residuals,
+        })
+    }
+    // linear interpolation, none outside the series
+    fn interpolate(series: &[[f64; 2]], t: f64) -> Option<f64> {
+        let idx = series.partition_point(|p| p[0] < t);
+        let after = series.get(idx)?;
+        if after[0] == t {
+            return Some(after[1]);
+        }
+        let before = series.get(idx.checked_sub(1)?)?;
+        let frac = (t - before[0]) / (after[0] - before[0]);
+        Some(before[1] + frac * (after[1] - before[1]))
+    }
+    // gaussian elimination with partial pivoting, none if the system is singular
+    fn solve(mut a: [[f64; 3]; 3], mut b: [f64; 3]) -> Option<[f64; 3]> {
+        for col in 0..3 {
+            let pivot = (col..3).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            b.swap(col, pivot);
+            let (pivot_row, pivot_b) = (a[col], b[col]);
+            for (row, rb) in a.iter_mut().zip(&mut b).skip(col + 1) {
+                let factor = row[col] / pivot_row[col];
+                for (cell, p) in row.iter_mut().zip(pivot_row) {
+                    *cell -= factor * p;
+                }
+                *rb -= factor * pivot_b;
+            }
+        }
+        let mut x = [0.0; 3];
+        for row in (0..3).rev() {
+            let rest: f64 = (row + 1..3).map(|k| a[row][k] * x[k]).sum();
+            x[row] = (b[row] - rest) / a[row][row];
+        }
+        Some(x)
+    }
+    // central difference of a series, for when acceleration isn't logged
+    pub fn differentiate(series: &[[f64; 2]]) -> Vec<[f64; 2]> {
+        series
+            .windows(3)
+            .filter(|w| w[2][0] > w[0][0])
+            .map(|w| [w[1][0], (w[2][1] - w[0][1]) / (w[2][0] - w[0][0])])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.001;
+
+    fn sampled(duration: f64, f: impl Fn(f64) -> f64) -> Vec<[f64; 2]> {
+        (0..=(duration / DT).round() as usize)
+            .map(|i| {
+                let t = i as f64 * DT;
+                [t, f(t)]
+            })
+            .collect()
+    }
+
+    // unit step response of a second order system with natural frequency wn
+    // and damping ratio zeta < 1
+    fn second_order(wn: f64, zeta: f64) -> impl Fn(f64) -> f64 {
+        let root = (1.0 - zeta * zeta).sqrt();
+        move |t| 1.0 - (-zeta * wn * t).exp() / root * (wn * root * t + zeta.acos()).sin()
+    }
+
+    #[test]
+    fn first_order_step() {
+        let tau = 0.5;
+        let measurement = sampled(5.0, |t| 1.0 - (-t / tau).exp());
+        let resp = StepResponse::analyse(&[[0.0, 1.0]], &measurement, 0.0, 5.0).unwrap();
+
+        assert_eq!(resp.initial, 0.0);
+        assert_eq!(resp.setpoint, 1.0);
+        // 10% to 90% of a first order step takes tau ln 9
+        assert!((resp.rise_time().unwrap() - tau * 9f64.ln()).abs() < 2.0 * DT);
+        assert_eq!(resp.overshoot, 0.0);
+        // within 2% after tau ln 50
+        assert!((resp.settling_time().unwrap() - tau * 50f64.ln()).abs() < 2.0 * DT);
+        assert!(resp.steady_state_error.abs() < 1e-3);
+    }
+
+    #[test]
+    fn second_order_step() {
+        let (wn, zeta) = (4.0, 0.3);
+        let root = (1.0 - zeta * zeta).sqrt();
+        let measurement = sampled(10.0, second_order(wn, zeta));
+        let resp = StepResponse::analyse(&[[0.0, 1.0]], &measurement, 0.0, 10.0).unwrap();
+
+        let overshoot = (-zeta * std::f64::consts::PI / root).exp() * 100.0;
+        assert!((resp.overshoot - overshoot).abs() < 0.1);
+        let peak_time = std::f64::consts::PI / (wn * root);
+        assert!((resp.peak[0] - peak_time).abs() < 2.0 * DT);
+        assert!(resp.rise_time().unwrap() < peak_time);
+        // the envelope is within 2% after ln(50 / root) / (zeta wn)
+        let settling = resp.settling_time().unwrap();
+        assert!(settling > peak_time);
+        assert!(settling <= (50.0 / root).ln() / (zeta * wn) + DT);
+        assert!(resp.steady_state_error.abs() < 1e-3);
+    }
+
+    #[test]
+    fn negative_step() {
+        let (wn, zeta) = (4.0, 0.3);
+        let response = second_order(wn, zeta);
+        // from 2 down to 1
+        let measurement = sampled(10.0, |t| 2.0 - response(t));
+        let resp = StepResponse::analyse(&[[0.0, 1.0]], &measurement, 0.0, 10.0).unwrap();
+
+        assert_eq!(resp.initial, 2.0);
+        assert_eq!(resp.setpoint, 1.0);
+        assert!(resp.rise_start.unwrap() < resp.rise_end.unwrap());
+        // the same shape as the positive step, just mirrored
+        let positive = sampled(10.0, second_order(wn, zeta));
+        let up = StepResponse::analyse(&[[0.0, 1.0]], &positive, 0.0, 10.0).unwrap();
+        assert!((resp.rise_time().unwrap() - up.rise_time().unwrap()).abs() < 1e-9);
+        assert!((resp.overshoot - up.overshoot).abs() < 1e-6);
+        assert!((resp.peak[1] - (2.0 - up.peak[1])).abs() < 1e-9);
+        assert_eq!(resp.settled_at, up.settled_at);
+        assert!((resp.steady_state_error + up.steady_state_error).abs() < 1e-9);
+    }
+
+    #[test]
+    fn oscillation_of_a_limit_cycle() {
+        let (period, amplitude) = (0.8, 0.5);
+        // settles into the cycle from below the centre
+        let samples = sampled(10.0, |t| {
+            2.0 - (-t).exp() + amplitude * (std::f64::consts::TAU * t / period).sin()
+        });
+        let oscillation = Oscillation::analyse(&samples, 4).unwrap();
+        assert!((oscillation.period - period).abs() < 1e-3);
+        assert!((oscillation.amplitude - amplitude).abs() < 1e-3);
+    }
+
+    #[test]
+    fn oscillation_needs_enough_cycles() {
+        let samples = sampled(2.2, |t| (std::f64::consts::TAU * t).sin());
+        assert!(Oscillation::analyse(&samples, 4).is_none());
+        assert!(Oscillation::analyse(&samples, 1).is_some());
+        assert!(Oscillation::analyse(&samples, 0).is_none());
+        assert!(Oscillation::analyse(&[], 4).is_none());
+    }
+
+    #[test]
+    fn ultimate_gain_from_the_describing_function() {
+        let oscillation = Oscillation {
+            period: 1.0,
+            amplitude: 0.5,
+        };
+        let ku = oscillation.ultimate_gain(2.0);
+        assert!((ku - 16.0 / std::f64::consts::PI).abs() < 1e-12);
+        // a larger swing for the same relay means a lower gain
+        let wider = Oscillation {
+            amplitude: 1.0,
+            ..oscillation
+        };
+        assert!((wider.ultimate_gain(2.0) - ku / 2.0).abs() < 1e-12);
+        assert!((oscillation.ultimate_gain(4.0) - 2.0 * ku).abs() < 1e-12);
+    }
+
+    #[test]
+    fn feed_forward_recovers_known_gains() {
+        let (ks, kv, ka) = (0.5, 2.0, 0.3);
+        let velocity = sampled(10.0, |t| 2.0 * (1.3 * t).sin());
+        let acceleration = sampled(10.0, |t| 2.6 * (1.3 * t).cos());
+        let voltage: Vec<[f64; 2]> = velocity
+            .iter()
+            .zip(&acceleration)
+            .map(|(&[t, v], &[_, a])| [t, ks * v.signum() + kv * v + ka * a])
+            .collect();
+
+        let ff = FeedForward::fit(&voltage, &velocity, &acceleration, 0.05).unwrap();
+        assert!((ff.ks - ks).abs() < 1e-9);
+        assert!((ff.kv - kv).abs() < 1e-9);
+        assert!((ff.ka - ka).abs() < 1e-9);
+        assert!((ff.r_squared - 1.0).abs() < 1e-9);
+        assert!(ff.residuals.iter().all(|r| r[2].abs() < 1e-9));
+        // samples slower than the minimum are left out
+        assert!(ff.residuals.iter().all(|r| r[1].abs() >= 0.05));
+        assert!(ff.residuals.len() < voltage.len());
+
+        // too slow to fit anything
+        assert!(FeedForward::fit(&voltage, &velocity, &acceleration, 10.0).is_none());
+    }
+
+    #[test]
+    fn feed_forward_ignores_standstill() {
+        let (ks, kv, ka) = (0.5, 2.0, 0.3);
+        // at rest with no voltage for the first second, then accelerating
+        let velocity = sampled(5.0, |t| if t < 1.0 { 0.0 } else { (t - 1.0).powi(2) });
+        let acceleration = sampled(5.0, |t| if t < 1.0 { 0.0 } else { 2.0 * (t - 1.0) });
+        let voltage: Vec<[f64; 2]> = velocity
+            .iter()
+            .zip(&acceleration)
+            .map(|(&[t, v], &[_, a])| {
+                let sign = if v > 0.0 { 1.0 } else { 0.0 };
+                [t, ks * sign + kv * v + ka * a]
+            })
+            .collect();
+
+        // standstill samples are kept without a minimum velocity
+        let ff = FeedForward::fit(&voltage, &velocity, &acceleration, 0.0).unwrap();
+        assert_eq!(ff.residuals.len(), voltage.len());
+        assert!((ff.ks - ks).abs() < 1e-9);
+        assert!((ff.kv - kv).abs() < 1e-9);
+        assert!((ff.ka - ka).abs() < 1e-9);
+        assert!((ff.r_squared - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn feed_forward_of_a_constant_voltage() {
+        let velocity = sampled(10.0, |t| 1.0 + 0.5 * t.sin());
+        let acceleration = sampled(10.0, |t| 0.5 * t.cos());
+        let voltage = sampled(10.0, |_| 1.0);
+
+        let ff = FeedForward::fit(&voltage, &velocity, &acceleration, 0.0).unwrap();
+        assert!((ff.ks - 1.0).abs() < 1e-9);
+        assert!(ff.kv.abs() < 1e-9);
+        assert!(ff.ka.abs() < 1e-9);
+        assert_eq!(ff.r_squared, 1.0);
+    }
+
+    #[test]
+    fn solve_pivots_and_rejects_singular_systems() {
+        // the first pivot is zero so a row swap is needed
+        let a = [[0.0, 2.0, 1.0], [1.0, 1.0, 1.0], [2.0, 1.0, 0.0]];
+        let x = FeedForward::solve(a, [7.0, 6.0, 4.0]).unwrap();
+        for (x, expected) in x.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((x - expected).abs() < 1e-12);
+        }
+
+        let singular = [[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 1.0]];
+        assert!(FeedForward::solve(singular, [1.0, 2.0, 3.0]).is_none());
+    }
+
+    #[test]
+    fn differentiate_central_difference() {
+        // exact for a line even with uneven spacing
+        let line: Vec<[f64; 2]> = [0.0, 0.1, 0.3, 0.35, 1.0]
+            .iter()
+            .map(|&t| [t, 3.0 * t + 1.0])
+            .collect();
+        let slope = FeedForward::differentiate(&line);
+        assert_eq!(slope.len(), 3);
+        for (p, t) in slope.iter().zip([0.1, 0.3, 0.35]) {
+            assert_eq!(p[0], t);
+            assert!((p[1] - 3.0).abs() < 1e-12);
+        }
+
+        // and for a parabola with even spacing
+        let parabola = sampled(1.0, |t| t * t);
+        for p in FeedForward::differentiate(&parabola) {
+            assert!((p[1] - 2.0 * p[0]).abs() < 1e-9);
+        }
+
+        // repeated timestamps would divide by zero
+        let repeated = [[0.0, 0.0], [0.0, 1.0], [0.0, 2.0], [1.0, 3.0]];
+        assert_eq!(FeedForward::differentiate(&repeated).len(), 1);
+    }
+}
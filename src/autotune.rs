@@ -0,0 +1,311 @@
+use std::collections::BTreeSet;
+
+use eframe::egui;
+use egui::Ui;
+
+use crate::analysis::Oscillation;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rule {
+    ZieglerNichols,
+    ZieglerNicholsPi,
+    SomeOvershoot,
+    NoOvershoot,
+    TyreusLuyben,
+    TyreusLuybenPi,
+}
+
+impl Rule {
+    const ALL: [Rule; 6] = [
+        Rule::ZieglerNichols,
+        Rule::ZieglerNicholsPi,
+        Rule::SomeOvershoot,
+        Rule::NoOvershoot,
+        Rule::TyreusLuyben,
+        Rule::TyreusLuybenPi,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Rule::ZieglerNichols => "Ziegler-Nichols",
+            Rule::ZieglerNicholsPi => "Ziegler-Nichols (PI)",
+            Rule::SomeOvershoot => "Some overshoot",
+            Rule::NoOvershoot => "No overshoot",
+            Rule::TyreusLuyben => "Tyreus-Luyben",
+            Rule::TyreusLuybenPi => "Tyreus-Luyben (PI)",
+        }
+    }
+    // kp, ki and kd from the ultimate gain and period
+    fn gains(&self, ku: f64, tu: f64) -> [f64; 3] {
+        // proportional gain, integral time and derivative time
+        let (kp, ti, td) = match self {
+            Rule::ZieglerNichols => (0.6 * ku, tu / 2.0, tu / 8.0),
+            Rule::ZieglerNicholsPi => (0.45 * ku, tu / 1.2, 0.0),
+            Rule::SomeOvershoot => (ku / 3.0, tu / 2.0, tu / 3.0),
+            Rule::NoOvershoot => (ku / 5.0, tu / 2.0, tu / 3.0),
+            Rule::TyreusLuyben => (ku / 2.2, 2.2 * tu, tu / 6.3),
+            Rule::TyreusLuybenPi => (ku / 3.2, 2.2 * tu, 0.0),
+        };
+        [kp, kp / ti, kp * td]
+    }
+}
+
+pub enum Action {
+    Apply(Vec<(String, f64)>),
+    // preset name
+    Save(String, Vec<(String, f64)>),
+}
+
+// oscillation being measured from telemetry
+struct Run {
+    controller: String,
+    relay: f64,
+    samples: Vec<[f64; 2]>,
+}
+
+struct Tuning {
+    controller: String,
+    ku: f64,
+    tu: f64,
+    // None if the ultimate gain and period were entered by hand
+    oscillation: Option<Oscillation>,
+}
+
+// suggests gains from the ultimate gain and period, either entered or measured
+// from the oscillation of a relay the robot runs. the protocol has no packet to
+// start a relay so the robot has to be put into oscillation by its own code
+pub struct Autotune {
+    // scalar telemetry seen so far as (plot, subplot)
+    signals: BTreeSet<(String, String)>,
+    signal: Option<(String, String)>,
+    relay: f64,
+    cycles: usize,
+    run: Option<Run>,
+    // ultimate gain and period entered by hand
+    manual: [f64; 2],
+    result: Option<Tuning>,
+    rule: Rule,
+    preset: String,
+}
+
+impl Default for Autotune {
+    fn default() -> Self {
+        Self {
+            signals: BTreeSet::new(),
+            signal: None,
+            relay: 1.0,
+            cycles: 4,
+            run: None,
+            manual: [1.0, 1.0],
+            result: None,
+            rule: Rule::ZieglerNichols,
+            preset: String::new(),
+        }
+    }
+}
+
+impl Autotune {
+    pub fn add_buffer(&mut self, plot: &str, subplot: &str, buffer: &communication::Buffer) {
+        let communication::Buffer::Scalar(v) = buffer else {
+            return;
+        };
+        let key = (plot.to_owned(), subplot.to_owned());
+        if let Some(run) = self
+            .run
+            .as_mut()
+            .filter(|_| self.signal.as_ref() == Some(&key))
+        {
+            for (time, value) in v {
+                let t = time.as_secs_f64();
+                if run.samples.last().is_some_and(|last| last[0] > t) {
+                    log::warn!(
+                        "Robot restarted during the measurement, discarding the oscillation so far"
+                    );
+                    run.samples.clear();
+                }
+                run.samples.push([t, *value]);
+            }
+            self.analyse();
+        }
+        self.signals.insert(key);
+    }
+    // finishes the run once enough cycles have been measured
+    fn analyse(&mut self) {
+        let Some(run) = &self.run else {
+            return;
+        };
+        if let Some(oscillation) = Oscillation::analyse(&run.samples, self.cycles) {
+            self.result = Some(Tuning {
+                controller: run.controller.clone(),
+                ku: oscillation.ultimate_gain(run.relay),
+                tu: oscillation.period,
+                oscillation: Some(oscillation),
+            });
+            self.run = None;
+        }
+    }
+    pub fn ui(&mut self, ui: &mut Ui, controller: &str) -> Option<Action> {
+        let mut action = None;
+        ui.label(
+            "Put the robot into relay oscillation from its own code, \
+             then measure the oscillation here",
+        );
+        egui::Grid::new("autotune settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Measurement");
+                let text = self
+                    .signal
+                    .as_ref()
+                    .map_or("select".to_owned(), |(p, s)| format!("{p}/{s}"));
+                egui::ComboBox::from_id_source("autotune signal")
+                    .selected_text(text)
+                    .show_ui(ui, |ui| {
+                        for (p, s) in &self.signals {
+                            ui.selectable_value(
+                                &mut self.signal,
+                                Some((p.clone(), s.clone())),
+                                format!("{p}/{s}"),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("Relay amplitude");
+                ui.add(egui::DragValue::new(&mut self.relay).speed(0.01))
+                    .on_hover_text("The output the robot's relay switches between ±");
+                ui.end_row();
+                ui.label("Cycles to measure");
+                ui.add(egui::DragValue::new(&mut self.cycles).clamp_range(2..=20));
+                ui.end_row();
+            });
+        self.relay = self.relay.abs();
+
+        match &self.run {
+            Some(run) => {
+                let mut stop = false;
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Measuring {}, {} samples",
+                        Self::controller_label(&run.controller),
+                        run.samples.len()
+                    ));
+                    stop = ui.button("Stop").clicked();
+                });
+                if stop {
+                    self.run = None;
+                }
+            }
+            None => {
+                let ready = self.signal.is_some() && self.relay > 0.0;
+                if ui
+                    .add_enabled(ready, egui::Button::new("Measure"))
+                    .on_disabled_hover_text("Select a measurement and a non zero amplitude")
+                    .clicked()
+                {
+                    self.run = Some(Run {
+                        controller: controller.to_owned(),
+                        relay: self.relay,
+                        samples: Vec::new(),
+                    });
+                    self.result = None;
+                }
+            }
+        }
+        egui::CollapsingHeader::new("Enter the ultimate gain and period").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let [ku, tu] = &mut self.manual;
+                ui.label("Ku");
+                ui.add(egui::DragValue::new(ku).speed(0.01));
+                ui.label("Tu");
+                ui.add(egui::DragValue::new(tu).speed(0.01).suffix("s"));
+                *ku = ku.max(0.0);
+                *tu = tu.max(1e-3);
+                if ui.button("Use").clicked() {
+                    self.run = None;
+                    self.result = Some(Tuning {
+                        controller: controller.to_owned(),
+                        ku: *ku,
+                        tu: *tu,
+                        oscillation: None,
+                    });
+                }
+            });
+        });
+
+        if let Some(run) = &self.run {
+            let line = egui_plot::Line::new(run.samples.clone());
+            egui_plot::Plot::new("autotune oscillation")
+                .view_aspect(2.0)
+                .show(ui, |plot_ui| plot_ui.line(line));
+        }
+
+        if let Some(result) = &self.result {
+            ui.separator();
+            if result.controller != controller {
+                ui.label(format!(
+                    "Result for {}",
+                    Self::controller_label(&result.controller)
+                ));
+                return action;
+            }
+            egui::Grid::new("autotune result")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Ultimate gain");
+                    ui.label(format!("{:.4}", result.ku));
+                    ui.end_row();
+                    ui.label("Ultimate period");
+                    ui.label(format!("{:.3}s", result.tu));
+                    ui.end_row();
+                    if let Some(oscillation) = result.oscillation {
+                        ui.label("Oscillation amplitude");
+                        ui.label(format!("{:.4}", oscillation.amplitude));
+                        ui.end_row();
+                    }
+                });
+            egui::ComboBox::from_id_source("autotune rule")
+                .selected_text(self.rule.label())
+                .show_ui(ui, |ui| {
+                    for rule in Rule::ALL {
+                        ui.selectable_value(&mut self.rule, rule, rule.label());
+                    }
+                });
+            let gains: Vec<(String, f64)> = ["kp", "ki", "kd"]
+                .into_iter()
+                .map(str::to_owned)
+                .zip(self.rule.gains(result.ku, result.tu))
+                .collect();
+            egui::Grid::new("autotune gains")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (term, value) in &gains {
+                        ui.label(term);
+                        ui.label(format!("{value:.5}"));
+                        ui.end_row();
+                    }
+                });
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    action = Some(Action::Apply(gains.clone()));
+                }
+                ui.text_edit_singleline(&mut self.preset);
+                if ui.button("Save as preset").clicked() {
+                    let name = if self.preset.is_empty() {
+                        format!("{} autotune", self.rule.label())
+                    } else {
+                        std::mem::take(&mut self.preset)
+                    };
+                    action = Some(Action::Save(name, gains));
+                }
+            });
+        }
+        action
+    }
+    fn controller_label(name: &str) -> &str {
+        if name.is_empty() {
+            "default"
+        } else {
+            name
+        }
+    }
+}
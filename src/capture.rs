@@ -0,0 +1,67 @@
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::logging::{Entry, Source};
+
+// forwards records to env_logger as before and also sends them to the logs window
+pub struct Capture {
+    inner: env_logger::Logger,
+    sender: Mutex<Sender<Entry>>,
+}
+
+impl Capture {
+    pub fn init() -> Receiver<Entry> {
+        let inner = env_logger::Builder::from_default_env().build();
+        let (sender, receiver) = mpsc::channel();
+        let max_level = inner.filter().max(LevelFilter::Info);
+        log::set_boxed_logger(Box::new(Self {
+            inner,
+            sender: Mutex::new(sender),
+        }))
+        .unwrap();
+        log::set_max_level(max_level);
+        receiver
+    }
+    // our own messages down to info and anything serious from dependencies
+    // are shown regardless of RUST_LOG
+    fn captured(metadata: &Metadata) -> bool {
+        if metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            metadata.level() <= Level::Info
+        } else {
+            metadata.level() <= Level::Warn
+        }
+    }
+}
+
+impl Log for Capture {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        Self::captured(metadata) || self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+        if Self::captured(record.metadata()) {
+            let module = record.module_path().unwrap_or(record.target());
+            let entry = Entry {
+                level: record.level(),
+                msg: record.args().to_string(),
+                timestamp: SystemTime::now(),
+                repeats: 1,
+                source: Source::App(module.to_owned()),
+            };
+            // the receiver only goes away when the app is closing
+            let _ = self.sender.lock().unwrap().send(entry);
+        }
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
@@ -0,0 +1,224 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+use eframe::egui;
+use egui::{Color32, Ui};
+use egui_plot::{HLine, Line, Plot, Points};
+
+use crate::{analysis::FeedForward, graph};
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+enum Source {
+    #[default]
+    Live,
+    File,
+}
+
+// fits drivetrain feed-forward constants from logged voltage, velocity and
+// acceleration, either from live graphs or a csv file
+pub struct Characterize {
+    source: Source,
+    // series are (graph, subplot) for live data and ("", column) for files
+    voltage: Option<(String, String)>,
+    velocity: Option<(String, String)>,
+    // none to differentiate the velocity instead
+    acceleration: Option<(String, String)>,
+    min_velocity: f64,
+    path: String,
+    columns: BTreeMap<String, Vec<[f64; 2]>>,
+    err: Option<Box<dyn Error>>,
+    fit: Option<FeedForward>,
+}
+
+impl Default for Characterize {
+    fn default() -> Self {
+        Self {
+            source: Source::default(),
+            voltage: None,
+            velocity: None,
+            acceleration: None,
+            // m/s, slower samples are mostly static friction
+            min_velocity: 0.05,
+            path: String::new(),
+            columns: BTreeMap::new(),
+            err: None,
+            fit: None,
+        }
+    }
+}
+
+impl Characterize {
+    // the first column is time and every other column becomes a series named
+    // after its header
+    fn load(path: &Path) -> Result<BTreeMap<String, Vec<[f64; 2]>>, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header: Vec<&str> = lines
+            .next()
+            .ok_or("file is empty")?
+            .split(',')
+            .map(str::trim)
+            .collect();
+        let mut columns: Vec<Vec<[f64; 2]>> = vec![Vec::new(); header.len()];
+        for (i, line) in lines.enumerate() {
+            let values = line
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("line {}: {e}", i + 2))?;
+            if values.len() != header.len() {
+                return Err(format!("line {}: expected {} columns", i + 2, header.len()).into());
+            }
+            for (column, value) in columns.iter_mut().zip(&values).skip(1) {
+                column.push([values[0], *value]);
+            }
+        }
+        Ok(header
+            .into_iter()
+            .zip(columns)
+            .skip(1)
+            .map(|(name, points)| (name.to_owned(), points))
+            .collect())
+    }
+    fn series<'a>(
+        &'a self,
+        graphing: &'a graph::Manager,
+        key: &Option<(String, String)>,
+    ) -> Option<&'a [[f64; 2]]> {
+        let (graph, subplot) = key.as_ref()?;
+        match self.source {
+            Source::Live => graphing.points(graph, subplot),
+            Source::File => self.columns.get(subplot).map(Vec::as_slice),
+        }
+    }
+    pub fn ui(&mut self, ui: &mut Ui, graphing: &graph::Manager) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.source, Source::Live, "Live");
+            ui.selectable_value(&mut self.source, Source::File, "File");
+        });
+        let available: Vec<(String, String)> = match self.source {
+            Source::Live => graphing.series(),
+            Source::File => {
+                ui.horizontal(|ui| {
+                    ui.label("CSV file:");
+                    ui.text_edit_singleline(&mut self.path);
+                    if ui.button("Load").clicked() {
+                        match Self::load(Path::new(&self.path)) {
+                            Ok(columns) => {
+                                self.columns = columns;
+                                self.err = None;
+                            }
+                            Err(e) => self.err = Some(e),
+                        }
+                    }
+                });
+                ui.weak("time in the first column, one series per column after it");
+                self.columns
+                    .keys()
+                    .map(|name| (String::new(), name.clone()))
+                    .collect()
+            }
+        };
+
+        egui::Grid::new("characterize series")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let label = |key: &Option<(String, String)>, none: &str| match key {
+                    Some((graph, subplot)) if graph.is_empty() => subplot.clone(),
+                    Some((graph, subplot)) => format!("{graph}/{subplot}"),
+                    None => none.to_owned(),
+                };
+                for (name, key, none) in [
+                    ("Voltage", &mut self.voltage, "select"),
+                    ("Velocity", &mut self.velocity, "select"),
+                    ("Acceleration", &mut self.acceleration, "from velocity"),
+                ] {
+                    ui.label(name);
+                    egui::ComboBox::from_id_source(("characterize", name))
+                        .selected_text(label(key, none))
+                        .show_ui(ui, |ui| {
+                            if name == "Acceleration" {
+                                ui.selectable_value(key, None, none);
+                            }
+                            for series in &available {
+                                let text = label(&Some(series.clone()), none);
+                                ui.selectable_value(key, Some(series.clone()), text);
+                            }
+                        });
+                    ui.end_row();
+                }
+                ui.label("Minimum velocity");
+                ui.add(
+                    egui::DragValue::new(&mut self.min_velocity)
+                        .speed(0.001)
+                        .clamp_range(0.0..=f64::INFINITY),
+                );
+                ui.end_row();
+            });
+
+        if ui.button("Fit").clicked() {
+            let voltage = self.series(graphing, &self.voltage);
+            let velocity = self.series(graphing, &self.velocity);
+            self.fit = match (voltage, velocity) {
+                (Some(voltage), Some(velocity)) => {
+                    let derived;
+                    let acceleration = match self.series(graphing, &self.acceleration) {
+                        Some(acceleration) => acceleration,
+                        None => {
+                            derived = FeedForward::differentiate(velocity);
+                            derived.as_slice()
+                        }
+                    };
+                    FeedForward::fit(voltage, velocity, acceleration, self.min_velocity)
+                }
+                _ => None,
+            };
+            self.err = self
+                .fit
+                .is_none()
+                .then(|| "Not enough overlapping samples to fit".into());
+        }
+        if let Some(e) = &self.err {
+            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+        }
+        if let Some(fit) = &self.fit {
+            Self::draw_fit(ui, fit);
+        }
+    }
+    fn draw_fit(ui: &mut Ui, fit: &FeedForward) {
+        ui.separator();
+        egui::Grid::new("characterize result")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (name, value) in [
+                    ("kS", fit.ks),
+                    ("kV", fit.kv),
+                    ("kA", fit.ka),
+                    ("R²", fit.r_squared),
+                ] {
+                    ui.label(name);
+                    ui.label(format!("{value:.5}"));
+                    ui.end_row();
+                }
+                ui.label("Samples");
+                ui.label(fit.residuals.len().to_string());
+                ui.end_row();
+            });
+
+        ui.label("Residual over time");
+        let over_time: Vec<[f64; 2]> = fit.residuals.iter().map(|r| [r[0], r[2]]).collect();
+        Plot::new("characterize residual time")
+            .view_aspect(3.0)
+            .show(ui, |plot_ui| {
+                plot_ui.hline(HLine::new(0.0).color(Color32::GRAY));
+                plot_ui.line(Line::new(over_time));
+            });
+        ui.label("Residual against velocity");
+        let over_velocity: Vec<[f64; 2]> = fit.residuals.iter().map(|r| [r[1], r[2]]).collect();
+        Plot::new("characterize residual velocity")
+            .view_aspect(3.0)
+            .show(ui, |plot_ui| {
+                plot_ui.hline(HLine::new(0.0).color(Color32::GRAY));
+                plot_ui.points(Points::new(over_velocity).radius(1.5));
+            });
+    }
+}
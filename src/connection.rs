@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeMap,
+    net::{SocketAddr, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use communication::{
+    packets::{ClientInfo, ToClient, ToRobot},
+    ClientListener,
+};
+use eframe::egui;
+use egui::{Color32, Context, Ui, Window};
+use serde::{Deserialize, Serialize};
+
+use crate::link::Link;
+
+const STORAGE_KEY: &str = "connection";
+// silence timeout in seconds when it is first turned on
+const DEFAULT_TIMEOUT: f64 = 5.0;
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Profile {
+    address: String,
+    port: u16,
+    client_name: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1".to_owned(),
+            port: 8733,
+            client_name: gethostname::gethostname().to_string_lossy().into_owned(),
+        }
+    }
+}
+
+impl Profile {
+    fn resolve(&self) -> Result<SocketAddr, String> {
+        (self.address.as_str(), self.port)
+            .to_socket_addrs()
+            .map_err(|e| format!("{}:{}: {e}", self.address, self.port))?
+            .next()
+            .ok_or_else(|| format!("{} did not resolve to an address", self.address))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    current: Profile,
+    profiles: BTreeMap<String, Profile>,
+    auto_reconnect: bool,
+    // seconds without a packet, while connecting or connected, before the
+    // robot is treated as lost. off by default as the protocol has no
+    // keep-alive, so a robot that isn't sending telemetry is quiet but still there
+    silence_timeout: Option<f64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            current: Profile::default(),
+            profiles: BTreeMap::new(),
+            auto_reconnect: true,
+            silence_timeout: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Disconnected,
+    // attempt counts consecutive failures for the backoff
+    Connecting { since: Instant, attempt: u32 },
+    Connected { last_packet: Instant },
+    Waiting { until: Instant, attempt: u32 },
+}
+
+// owns the link to the robot and reconnects it when it goes quiet
+pub struct Connection {
+    settings: Settings,
+    listener: Option<ClientListener>,
+    state: State,
+    pub open: bool,
+    new_profile: String,
+    err: Option<String>,
+    link: Link,
+}
+
+impl Connection {
+    // connects straight away with the last used profile
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut connection = Self {
+            settings: storage
+                .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+                .unwrap_or_default(),
+            listener: None,
+            state: State::Disconnected,
+            open: false,
+            new_profile: String::new(),
+            err: None,
+            link: Link::default(),
+        };
+        connection.connect(0);
+        connection
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, &self.settings);
+    }
+    fn connect(&mut self, attempt: u32) {
+        self.listener = None;
+        self.link = Link::default();
+        let profile = &self.settings.current;
+        match profile.resolve() {
+            Ok(addr) => {
+                log::info!("Connecting to {addr} as {}", profile.client_name);
+                self.listener = Some(ClientListener::new(
+                    addr,
+                    ClientInfo::new(profile.client_name.clone()),
+                ));
+                self.state = State::Connecting {
+                    since: Instant::now(),
+                    attempt,
+                };
+                self.err = None;
+            }
+            Err(e) => {
+                log::warn!("Failed to connect: {e}");
+                self.err = Some(e);
+                self.state = State::Disconnected;
+            }
+        }
+    }
+    pub fn client_name(&self) -> &str {
+        &self.settings.current.client_name
+    }
+    fn disconnect(&mut self) {
+        self.listener = None;
+        self.state = State::Disconnected;
+    }
+    fn backoff(attempt: u32) -> Duration {
+        MIN_BACKOFF
+            .saturating_mul(1 << attempt.min(16))
+            .min(MAX_BACKOFF)
+    }
+    pub fn get_packets(&mut self) -> Vec<ToClient> {
+        let now = Instant::now();
+        let pkts = match &mut self.listener {
+            Some(listener) => listener.get_packets(),
+            None => Vec::new(),
+        };
+        self.link.record(&pkts);
+        // any packet shows the link is alive
+        if !pkts.is_empty() {
+            self.state = State::Connected { last_packet: now };
+        }
+
+        let timeout = self.settings.silence_timeout.map(Duration::from_secs_f64);
+        let timed_out = |since: Instant| timeout.is_some_and(|timeout| now - since > timeout);
+        let lost = match self.state {
+            State::Connecting { since, attempt } if timed_out(since) => Some(attempt),
+            State::Connected { last_packet } if timed_out(last_packet) => Some(0),
+            State::Waiting { until, attempt } if now >= until => {
+                self.connect(attempt);
+                None
+            }
+            _ => None,
+        };
+        if let Some(attempt) = lost.filter(|_| self.settings.auto_reconnect) {
+            let backoff = Self::backoff(attempt);
+            log::warn!(
+                "No packets from the robot, reconnecting in {:.1}s",
+                backoff.as_secs_f64()
+            );
+            self.listener = None;
+            self.state = State::Waiting {
+                until: now + backoff,
+                attempt: attempt + 1,
+            };
+        }
+        pkts
+    }
+    pub fn send_packet(&mut self, packet: ToRobot) {
+        match &mut self.listener {
+            Some(listener) => listener.send_packet(packet),
+            None => log::warn!("Not connected, dropping packet for the robot"),
+        }
+    }
+    fn status(&self) -> (Color32, String) {
+        let now = Instant::now();
+        match self.state {
+            State::Disconnected => (Color32::GRAY, "Disconnected".to_owned()),
+            State::Connecting { .. } => (Color32::YELLOW, "Connecting".to_owned()),
+            State::Connected { last_packet } if now - last_packet > Duration::from_secs(1) => (
+                Color32::YELLOW,
+                format!("No data for {}s", (now - last_packet).as_secs()),
+            ),
+            State::Connected { .. } => (Color32::GREEN, "Connected".to_owned()),
+            State::Waiting { until, .. } => (
+                Color32::RED,
+                format!(
+                    "Reconnecting in {:.0}s",
+                    until.saturating_duration_since(now).as_secs_f64().ceil()
+                ),
+            ),
+        }
+    }
+    // status indicator for the menu bar, clicking it opens the dialog
+    pub fn draw_status(&mut self, ui: &mut Ui) {
+        let (color, text) = self.status();
+        let profile = &self.settings.current;
+        let resp = ui
+            .add(
+                egui::Button::new(egui::RichText::new(format!("● {text}")).color(color))
+                    .frame(false),
+            )
+            .on_hover_text(format!(
+                "{}:{} as {}",
+                profile.address, profile.port, profile.client_name
+            ));
+        if resp.clicked() {
+            self.open = true;
+        }
+        // keep the status text and timeouts moving without user input, often
+        // enough that a frame delay isn't mistaken for a gap in the link
+        if self.state != State::Disconnected {
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+    }
+    pub fn draw(&mut self, ctx: &Context) {
+        let mut open = self.open;
+        Window::new("Connection")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| self.ui(ui));
+        self.open = open;
+    }
+    fn ui(&mut self, ui: &mut Ui) {
+        let profile = &mut self.settings.current;
+        egui::Grid::new("connection profile")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Address");
+                ui.text_edit_singleline(&mut profile.address);
+                ui.end_row();
+                ui.label("Port");
+                ui.add(egui::DragValue::new(&mut profile.port));
+                ui.end_row();
+                ui.label("Client name");
+                ui.text_edit_singleline(&mut profile.client_name);
+                ui.end_row();
+            });
+        ui.checkbox(&mut self.settings.auto_reconnect, "Reconnect automatically");
+        ui.horizontal(|ui| {
+            let mut enabled = self.settings.silence_timeout.is_some();
+            let mut secs = self.settings.silence_timeout.unwrap_or(DEFAULT_TIMEOUT);
+            ui.checkbox(&mut enabled, "Treat as lost after no packets for")
+                .on_hover_text(
+                    "The robot sends no keep-alives, only turn this on if it always sends telemetry",
+                );
+            ui.add_enabled(
+                enabled,
+                egui::DragValue::new(&mut secs)
+                    .clamp_range(1.0..=60.0)
+                    .suffix("s"),
+            );
+            self.settings.silence_timeout = enabled.then_some(secs);
+        });
+
+        ui.horizontal(|ui| {
+            let (color, text) = self.status();
+            ui.colored_label(color, text);
+            if self.listener.is_some() || matches!(self.state, State::Waiting { .. }) {
+                if ui.button("Disconnect").clicked() {
+                    self.disconnect();
+                }
+                if ui.button("Reconnect").clicked() {
+                    self.connect(0);
+                }
+            } else if ui.button("Connect").clicked() {
+                self.connect(0);
+            }
+        });
+        if let Some(e) = &self.err {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+
+        ui.separator();
+        ui.label("Profiles");
+        let (mut select, mut remove) = (None, None);
+        for (name, profile) in &self.settings.profiles {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(name)
+                    .on_hover_text(format!("{}:{}", profile.address, profile.port))
+                    .clicked()
+                {
+                    select = Some(profile.clone());
+                }
+                if ui
+                    .small_button("🗑")
+                    .on_hover_text("Delete profile")
+                    .clicked()
+                {
+                    remove = Some(name.clone());
+                }
+            });
+        }
+        if let Some(profile) = select {
+            self.settings.current = profile;
+            self.connect(0);
+        }
+        if let Some(name) = remove {
+            self.settings.profiles.remove(&name);
+        }
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_profile);
+            if ui.button("Save as profile").clicked() && !self.new_profile.is_empty() {
+                self.settings.profiles.insert(
+                    std::mem::take(&mut self.new_profile),
+                    self.settings.current.clone(),
+                );
+            }
+        });
+
+        ui.separator();
+        egui::CollapsingHeader::new("Link health")
+            .default_open(true)
+            .show(ui, |ui| self.link.ui(ui));
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    f64::consts::TAU,
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+use egui::{Context, DragValue, Ui};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    drive_sim::{self, Drivetrain, Executor, MAX_DURATION},
+    rng::Rng,
+    routine::{self, wrap_angle, Action, Pose},
+    vec::Vec2,
+};
+
+const STORAGE_KEY: &str = "drift";
+// the same seed every time so the spread doesn't jump around while editing
+const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+const ELLIPSE_POINTS: usize = 48;
+// inputs have to stay the same this long before they are simulated, so
+// dragging a value doesn't rerun it every frame
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+// standard deviations of what the robot actually does compared to what its
+// odometry measures
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Noise {
+    // error in the distance of each drive, as a fraction of it
+    pub slip: f64,
+    // heading drift while driving, in radians per √m
+    pub heading: f64,
+    // error in the angle of each turn, as a fraction of it
+    pub turn: f64,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            slip: 0.02,
+            heading: 1f64.to_radians(),
+            turn: 0.01,
+        }
+    }
+}
+
+// where the robot actually ended up after an action across all runs
+#[derive(Debug, Clone)]
+pub struct Spread {
+    pub action: usize,
+    pub positions: Vec<Vec2>,
+    pub mean: Vec2,
+    // covariance of the positions, [xx, xy, yy]
+    pub cov: [f64; 3],
+    // standard deviation of the heading
+    pub heading: f64,
+}
+
+impl Spread {
+    fn new(action: usize, poses: &[Pose], planned: Pose) -> Self {
+        let n = poses.len().max(1) as f64;
+        let positions: Vec<Vec2> = poses.iter().map(|pose| pose.pos).collect();
+        let mean = positions.iter().fold(Vec2::ZERO, |sum, &pos| sum + pos) / n;
+        let mut cov = [0.0; 3];
+        for pos in &positions {
+            let d = *pos - mean;
+            cov[0] += d.x() * d.x() / n;
+            cov[1] += d.x() * d.y() / n;
+            cov[2] += d.y() * d.y() / n;
+        }
+        // relative to the plan so headings either side of ±π don't average out
+        let errors: Vec<f64> = poses
+            .iter()
+            .map(|pose| wrap_angle(pose.heading - planned.heading))
+            .collect();
+        let mean_error = errors.iter().sum::<f64>() / n;
+        let heading = (errors.iter().map(|e| (e - mean_error).powi(2)).sum::<f64>() / n).sqrt();
+        Self {
+            action,
+            positions,
+            mean,
+            cov,
+            heading,
+        }
+    }
+    // standard deviations along the major and minor axes and the angle of the
+    // major axis from +x
+    pub fn axes(&self) -> ([f64; 2], f64) {
+        let [xx, xy, yy] = self.cov;
+        let mid = 0.5 * (xx + yy);
+        let diff = (0.25 * (xx - yy).powi(2) + xy * xy).sqrt();
+        let major = (mid + diff).max(0.0).sqrt();
+        let minor = (mid - diff).max(0.0).sqrt();
+        ([major, minor], 0.5 * (2.0 * xy).atan2(xx - yy))
+    }
+    // outline containing positions within `sigmas` standard deviations
+    pub fn ellipse(&self, sigmas: f64) -> Vec<[f64; 2]> {
+        let ([major, minor], angle) = self.axes();
+        let (s, c) = angle.sin_cos();
+        (0..=ELLIPSE_POINTS)
+            .map(|i| {
+                let t = TAU * i as f64 / ELLIPSE_POINTS as f64;
+                let (x, y) = (sigmas * major * t.cos(), sigmas * minor * t.sin());
+                [self.mean.x() + x * c - y * s, self.mean.y() + x * s + y * c]
+            })
+            .collect()
+    }
+}
+
+// motion of the drivetrain over one simulation step as odometry measures it
+struct Step {
+    // actions finished before the drivetrain moved
+    finished: Range<usize>,
+    action: usize,
+    distance: f64,
+    turn: f64,
+}
+
+// runs the routine `runs` times with noise, returns the spread after each
+// action that was reached
+pub fn simulate(
+    actions: &[Action],
+    drivetrain: Drivetrain,
+    noise: Noise,
+    runs: usize,
+) -> Vec<Spread> {
+    let planned = routine::plan(actions);
+    let is_start = |i: usize| matches!(actions[i], Action::StartAt { .. });
+
+    // the drivetrain follows its odometry, so it takes the same steps every
+    // run and only what they do to the robot differs
+    let mut executor = Executor::new(drivetrain, actions);
+    let mut steps = vec![Step {
+        finished: 0..executor.action,
+        action: executor.action,
+        distance: 0.0,
+        turn: 0.0,
+    }];
+    while !executor.finished() && executor.time < MAX_DURATION {
+        let before = executor.sample();
+        executor.step(drive_sim::DT);
+        let after = executor.sample();
+        let finished = before.action..after.action;
+        let start = finished
+            .clone()
+            .rev()
+            .find(|&i| is_start(i))
+            .map_or(before.pose, |i| planned[i]);
+        steps.push(Step {
+            finished,
+            action: after.action,
+            distance: (after.pose.pos - start.pos).dot(&start.forward()),
+            turn: after.pose.heading - start.heading,
+        });
+    }
+
+    // the executor only moves past the last action on the step after it,
+    // which the loop never takes
+    let reached = if executor.finished() {
+        actions.len()
+    } else {
+        executor.action
+    };
+    steps.push(Step {
+        finished: executor.action..reached,
+        action: reached,
+        distance: 0.0,
+        turn: 0.0,
+    });
+    let mut poses = vec![Vec::with_capacity(runs); reached];
+    let mut rng = Rng::new(SEED);
+    for _ in 0..runs {
+        // each action slips and over or under turns by its own amount
+        let scales: Vec<[f64; 2]> = actions
+            .iter()
+            .map(|_| {
+                [
+                    1.0 + noise.slip * rng.gaussian(),
+                    1.0 + noise.turn * rng.gaussian(),
+                ]
+            })
+            .collect();
+        let mut pose = Pose::default();
+        for step in &steps {
+            for i in step.finished.clone() {
+                if is_start(i) {
+                    pose = planned[i];
+                }
+                poses[i].push(pose);
+            }
+            let Some([slip, turn]) = scales.get(step.action) else {
+                continue;
+            };
+            pose.heading += step.turn * turn;
+            if step.distance != 0.0 {
+                pose.heading += noise.heading * step.distance.abs().sqrt() * rng.gaussian();
+                pose.pos = pose.pos + pose.forward() * (step.distance * slip);
+            }
+        }
+    }
+
+    poses
+        .iter()
+        .enumerate()
+        .map(|(i, poses)| Spread::new(i, poses, planned[i]))
+        .collect()
+}
+
+// what the simulated spreads were made from
+struct Inputs {
+    actions: Vec<Action>,
+    drivetrain: Drivetrain,
+    noise: Noise,
+    runs: usize,
+}
+
+impl Inputs {
+    fn matches(
+        &self,
+        actions: &[Action],
+        drivetrain: Drivetrain,
+        noise: Noise,
+        runs: usize,
+    ) -> bool {
+        self.actions == actions
+            && self.drivetrain == drivetrain
+            && self.noise == noise
+            && self.runs == runs
+    }
+}
+
+// monte carlo preview of how far odometry drifts along the routine
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Drift {
+    enabled: bool,
+    runs: usize,
+    noise: Noise,
+    #[serde(skip)]
+    simulated: Option<Inputs>,
+    // changed inputs waiting to be simulated and when they last changed
+    #[serde(skip)]
+    pending: Option<(Inputs, Instant)>,
+    #[serde(skip)]
+    spreads: Vec<Spread>,
+}
+
+impl Default for Drift {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            runs: 200,
+            noise: Noise::default(),
+            simulated: None,
+            pending: None,
+            spreads: Vec::new(),
+        }
+    }
+}
+
+impl Drift {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+    pub fn spreads(&self) -> &[Spread] {
+        &self.spreads
+    }
+    // resimulates once the inputs have settled after a change, returns true if
+    // the spreads changed
+    pub fn update(&mut self, ctx: &Context, actions: &[Action], drivetrain: Drivetrain) -> bool {
+        if !self.enabled {
+            self.pending = None;
+            if self.simulated.take().is_none() {
+                return false;
+            }
+            self.spreads.clear();
+            return true;
+        }
+        let (noise, runs) = (self.noise, self.runs);
+        let matches = |inputs: &Inputs| inputs.matches(actions, drivetrain, noise, runs);
+        if self.simulated.as_ref().is_some_and(matches) {
+            self.pending = None;
+            return false;
+        }
+        match &self.pending {
+            Some((inputs, changed)) if matches(inputs) => {
+                let waited = changed.elapsed();
+                if waited < DEBOUNCE {
+                    ctx.request_repaint_after(DEBOUNCE - waited);
+                    return false;
+                }
+            }
+            _ => {
+                let inputs = Inputs {
+                    actions: actions.to_vec(),
+                    drivetrain,
+                    noise,
+                    runs,
+                };
+                self.pending = Some((inputs, Instant::now()));
+                ctx.request_repaint_after(DEBOUNCE);
+                return false;
+            }
+        }
+        let Some((inputs, _)) = self.pending.take() else {
+            return false;
+        };
+        self.spreads = simulate(actions, drivetrain, noise, runs);
+        self.simulated = Some(inputs);
+        true
+    }
+    pub fn ui(&mut self, ui: &mut Ui, actions: &[Action]) {
+        ui.checkbox(&mut self.enabled, "Show on the field");
+        let percent = |ui: &mut Ui, value: &mut f64| {
+            let mut percent = *value * 100.0;
+            if ui
+                .add(
+                    DragValue::new(&mut percent)
+                        .speed(0.1)
+                        .clamp_range(0.0..=50.0)
+                        .suffix("%"),
+                )
+                .changed()
+            {
+                *value = percent / 100.0;
+            }
+        };
+        egui::Grid::new("drift noise")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Runs");
+                ui.add(
+                    DragValue::new(&mut self.runs)
+                        .speed(1.0)
+                        .clamp_range(10..=2000),
+                );
+                ui.end_row();
+                ui.label("Wheel slip");
+                percent(ui, &mut self.noise.slip);
+                ui.end_row();
+                ui.label("Heading drift");
+                let mut degrees = self.noise.heading.to_degrees();
+                if ui
+                    .add(
+                        DragValue::new(&mut degrees)
+                            .speed(0.05)
+                            .clamp_range(0.0..=30.0)
+                            .suffix("°/√m"),
+                    )
+                    .changed()
+                {
+                    self.noise.heading = degrees.to_radians();
+                }
+                ui.end_row();
+                ui.label("Turn error");
+                percent(ui, &mut self.noise.turn);
+                ui.end_row();
+            });
+
+        if !self.enabled {
+            return;
+        }
+        ui.separator();
+        egui::Grid::new("drift spreads")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("After");
+                ui.strong("1σ major");
+                ui.strong("1σ minor");
+                ui.strong("1σ heading");
+                ui.end_row();
+                for spread in &self.spreads {
+                    let ([major, minor], _) = spread.axes();
+                    let name = actions.get(spread.action).map_or("", Action::name);
+                    ui.label(format!("{} {name}", spread.action));
+                    ui.label(format!("{:.1} cm", major * 100.0));
+                    ui.label(format!("{:.1} cm", minor * 100.0));
+                    ui.label(format!("{:.1}°", spread.heading.to_degrees()));
+                    ui.end_row();
+                }
+            });
+    }
+}
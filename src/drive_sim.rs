@@ -0,0 +1,356 @@
+use std::{collections::VecDeque, time::Instant};
+
+use eframe::egui;
+use egui::{DragValue, Ui};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    routine::{self, wrap_angle, Action, Pose},
+    vec::Vec2,
+};
+
+const STORAGE_KEY: &str = "drive_sim";
+pub const SIM_ROBOT: &str = "sim";
+// simulation time step in seconds
+pub const DT: f64 = 0.005;
+// simulated routines are cut off after this many seconds, e.g. if one never
+// finishes
+pub const MAX_DURATION: f64 = 120.0;
+// longest frame simulated in real time, in seconds
+const MAX_FRAME: f64 = 0.1;
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Drivetrain {
+    // wheel speed in m/s
+    pub max_speed: f64,
+    // wheel acceleration in m/s²
+    pub max_accel: f64,
+    pub track_width: f64,
+    // width and length drawn on the field
+    pub size: [f64; 2],
+}
+
+impl Default for Drivetrain {
+    fn default() -> Self {
+        Self {
+            max_speed: 1.5,
+            max_accel: 3.0,
+            track_width: 0.3,
+            size: [0.3302, 0.381],
+        }
+    }
+}
+
+// motion of the wheels, both turn the same way for a drive and opposite ways
+// for a turn in place
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    // signed distance
+    Drive(f64),
+    // signed angle
+    Turn(f64),
+}
+
+// state of the drivetrain at a point in time
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub time: f64,
+    pub action: usize,
+    pub pose: Pose,
+    pub velocity: f64,
+    pub angular: f64,
+}
+
+impl Sample {
+    pub fn describe(&self, actions: &[Action]) -> String {
+        let action = match actions.get(self.action) {
+            Some(action) => format!("action {} {}", self.action, action.name()),
+            None => "finished".to_owned(),
+        };
+        format!(
+            "{:.2}s  {action}  ({:.2}, {:.2}) {:.0}°  {:.2} m/s {:.0}°/s",
+            self.time,
+            self.pose.pos.x(),
+            self.pose.pos.y(),
+            wrap_angle(self.pose.heading).to_degrees(),
+            self.velocity,
+            self.angular.to_degrees(),
+        )
+    }
+}
+
+// runs an action list on a differential drive with trapezoidal wheel profiles
+pub struct Executor {
+    drivetrain: Drivetrain,
+    actions: Vec<Action>,
+    planned: Vec<Pose>,
+    pub time: f64,
+    pub pose: Pose,
+    // speed along the heading and turn rate
+    pub velocity: f64,
+    pub angular: f64,
+    // action being executed, the number of actions once finished
+    pub action: usize,
+    next: usize,
+    segments: VecDeque<Segment>,
+    // distance each wheel has covered in the current segment
+    progress: f64,
+    wheel_speed: f64,
+}
+
+impl Executor {
+    pub fn new(drivetrain: Drivetrain, actions: &[Action]) -> Self {
+        let mut executor = Self {
+            drivetrain,
+            actions: actions.to_vec(),
+            planned: routine::plan(actions),
+            time: 0.0,
+            pose: Pose::default(),
+            velocity: 0.0,
+            angular: 0.0,
+            action: 0,
+            next: 0,
+            segments: VecDeque::new(),
+            progress: 0.0,
+            wheel_speed: 0.0,
+        };
+        // a routine normally starts by placing the robot
+        executor.load_segments();
+        executor
+    }
+    pub fn sample(&self) -> Sample {
+        Sample {
+            time: self.time,
+            action: self.action,
+            pose: self.pose,
+            velocity: self.velocity,
+            angular: self.angular,
+        }
+    }
+    pub fn finished(&self) -> bool {
+        self.segments.is_empty() && self.next == self.actions.len()
+    }
+    // starts actions until one needs the robot to move, false if none are left
+    fn load_segments(&mut self) -> bool {
+        while self.segments.is_empty() {
+            if self.next == self.actions.len() {
+                self.action = self.actions.len();
+                return false;
+            }
+            self.action = self.next;
+            self.next += 1;
+            self.segments = self.segments_for(self.action);
+        }
+        true
+    }
+    fn segments_for(&mut self, i: usize) -> VecDeque<Segment> {
+        let pose = self.pose;
+        let planned = self.planned[i];
+        match self.actions[i] {
+            Action::StartAt { .. } => {
+                self.pose = planned;
+                VecDeque::new()
+            }
+            Action::MoveRel { rel } => [Segment::Drive(rel)].into(),
+            Action::MoveRelAbs { .. } => {
+                // only the part of the way to the planned position that can be
+                // driven straight
+                let d = planned.pos - pose.pos;
+                [Segment::Drive(d.dot(&pose.forward()))].into()
+            }
+            Action::MoveTo { pos } => match pose.heading_to(pos.into()) {
+                Some(heading) => [
+                    Segment::Turn(wrap_angle(heading - pose.heading)),
+                    Segment::Drive((Vec2::from(pos) - pose.pos).mag()),
+                ]
+                .into(),
+                None => VecDeque::new(),
+            },
+            Action::TurnRel { angle } => [Segment::Turn(angle)].into(),
+            Action::TurnRelAbs { .. } => [Segment::Turn(planned.heading - pose.heading)].into(),
+            Action::TurnTo { heading } => {
+                [Segment::Turn(wrap_angle(heading - pose.heading))].into()
+            }
+        }
+    }
+    pub fn step(&mut self, dt: f64) {
+        self.time += dt;
+        self.velocity = 0.0;
+        self.angular = 0.0;
+        if !self.load_segments() {
+            return;
+        }
+        let segment = self.segments[0];
+        let half_track = 0.5 * self.drivetrain.track_width;
+        let (signed, target) = match segment {
+            Segment::Drive(d) => (d, d.abs()),
+            Segment::Turn(a) => (a, a.abs() * half_track),
+        };
+        let remaining = (target - self.progress).max(0.0);
+
+        // accelerate up to the speed that can still stop in the remaining distance
+        let accel = self.drivetrain.max_accel;
+        let limit = self
+            .drivetrain
+            .max_speed
+            .min((2.0 * accel * remaining).sqrt());
+        self.wheel_speed = (self.wheel_speed + accel * dt).min(limit);
+        let ds = (self.wheel_speed * dt).min(remaining);
+        self.progress += ds;
+
+        let sign = signed.signum();
+        match segment {
+            Segment::Drive(_) => {
+                self.pose.pos = self.pose.pos + self.pose.forward() * (sign * ds);
+                self.velocity = sign * self.wheel_speed;
+            }
+            Segment::Turn(_) => {
+                self.pose.heading += sign * ds / half_track;
+                self.angular = sign * self.wheel_speed / half_track;
+            }
+        }
+
+        if self.progress >= target - 1e-9 {
+            self.segments.pop_front();
+            self.progress = 0.0;
+            self.wheel_speed = 0.0;
+        }
+    }
+}
+
+// plays the routine on the simulated drivetrain in real time
+pub struct DriveSim {
+    drivetrain: Drivetrain,
+    executor: Option<Executor>,
+    playing: bool,
+    speed: f64,
+    last_frame: Option<Instant>,
+}
+
+impl DriveSim {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        Self {
+            drivetrain: storage
+                .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+                .unwrap_or_default(),
+            executor: None,
+            playing: false,
+            speed: 1.0,
+            last_frame: None,
+        }
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, &self.drivetrain);
+    }
+    pub fn drivetrain(&self) -> Drivetrain {
+        self.drivetrain
+    }
+    // back to the start, the next play picks up changes to the routine
+    pub fn reset(&mut self) {
+        self.executor = None;
+        self.playing = false;
+    }
+    // advances to the current time, returns the simulated pose if running
+    pub fn update(&mut self, ctx: &egui::Context, actions: &[Action]) -> Option<Pose> {
+        let now = Instant::now();
+        let last_frame = self.last_frame.replace(now);
+        if self.playing {
+            let executor = self
+                .executor
+                .get_or_insert_with(|| Executor::new(self.drivetrain, actions));
+            if let Some(last) = last_frame {
+                // a long frame, e.g. while minimised, shouldn't skip the routine
+                let elapsed = (now - last).as_secs_f64().min(MAX_FRAME);
+                let end = executor.time + elapsed * self.speed;
+                while executor.time < end && !executor.finished() {
+                    executor.step(DT);
+                }
+            }
+            if executor.finished() {
+                self.playing = false;
+            }
+            ctx.request_repaint();
+        }
+        self.executor.as_ref().map(|executor| executor.pose)
+    }
+    pub fn ui(&mut self, ui: &mut Ui, actions: &[Action]) {
+        let mut changed = false;
+        egui::Grid::new("drivetrain").num_columns(2).show(ui, |ui| {
+            let drivetrain = &mut self.drivetrain;
+            ui.label("Max speed");
+            changed |= ui
+                .add(
+                    DragValue::new(&mut drivetrain.max_speed)
+                        .speed(0.01)
+                        .clamp_range(0.05..=10.0)
+                        .suffix(" m/s"),
+                )
+                .changed();
+            ui.end_row();
+            ui.label("Max acceleration");
+            changed |= ui
+                .add(
+                    DragValue::new(&mut drivetrain.max_accel)
+                        .speed(0.05)
+                        .clamp_range(0.05..=50.0)
+                        .suffix(" m/s²"),
+                )
+                .changed();
+            ui.end_row();
+            ui.label("Track width");
+            changed |= ui
+                .add(
+                    DragValue::new(&mut drivetrain.track_width)
+                        .speed(0.005)
+                        .clamp_range(0.05..=2.0)
+                        .suffix(" m"),
+                )
+                .changed();
+            ui.end_row();
+            ui.label("Size");
+            ui.horizontal(|ui| {
+                for length in &mut drivetrain.size {
+                    ui.add(
+                        DragValue::new(length)
+                            .speed(0.005)
+                            .clamp_range(0.05..=2.0)
+                            .suffix(" m"),
+                    );
+                }
+            });
+            ui.end_row();
+        });
+        if changed {
+            self.reset();
+        }
+
+        ui.horizontal(|ui| {
+            let label = if self.playing { "⏸" } else { "▶" };
+            if ui.button(label).clicked() {
+                if self.executor.as_ref().is_some_and(Executor::finished) {
+                    self.executor = None;
+                }
+                self.playing = !self.playing;
+            }
+            if ui.button("⏮").on_hover_text("Back to the start").clicked() {
+                self.reset();
+            }
+            ui.add(
+                egui::Slider::new(&mut self.speed, MIN_SPEED..=MAX_SPEED)
+                    .logarithmic(true)
+                    .suffix("×"),
+            );
+        });
+        match &self.executor {
+            Some(executor) => {
+                ui.label(executor.sample().describe(actions));
+            }
+            None => {
+                ui.weak("Not running");
+            }
+        }
+    }
+}
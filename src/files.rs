@@ -0,0 +1,27 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// name of the window, eframe also keeps the app's storage under it
+pub const APP_NAME: &str = "Path Editor";
+
+// directory for files the app writes, next to eframe's storage so it doesn't
+// depend on where the app was launched from
+pub fn data_dir(name: &str) -> PathBuf {
+    eframe::storage_dir(APP_NAME).unwrap_or_default().join(name)
+}
+
+// files in `dir` with the given extension and their size, sorted by name
+pub fn list(dir: &Path, extension: &str) -> io::Result<Vec<(PathBuf, u64)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == extension) {
+            files.push((path, entry.metadata()?.len()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
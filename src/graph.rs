@@ -1,23 +1,39 @@
 //use communication::plot;
 use eframe::egui;
-use egui::{containers::Window, Context, Ui};
+use egui::Ui;
 use std::collections::HashMap;
 
+use crate::analysis::StepResponse;
+
 #[derive(Debug, Default)]
 pub struct Manager {
     graphs: HashMap<String, Graph>,
+    // set when a graph detects the robot restarting, cleared by `take_restarted`
+    restarted: bool,
 }
 
 impl Manager {
     fn add_point(&mut self, plot_name: &str, subplot_name: &str, point: [f64; 2]) {
         match self.graphs.get_mut(plot_name) {
-            Some(graph) => graph.add_point(subplot_name, point),
+            Some(graph) => self.restarted |= graph.add_point(subplot_name, point),
             None => {
-                self.graphs
-                    .insert(plot_name.to_owned(), Graph::new(plot_name.to_owned()));
+                let mut graph = Graph::new(plot_name.to_owned());
+                graph.add_point(subplot_name, point);
+                self.graphs.insert(plot_name.to_owned(), graph);
             }
         }
     }
+    fn add_vector(
+        &mut self,
+        plot_name: &str,
+        subplot_name: &str,
+        point: (f64, [f64; 3]),
+        dims: usize,
+    ) {
+        if let Some(graph) = self.graphs.get_mut(plot_name) {
+            graph.add_vector(subplot_name, point, dims);
+        }
+    }
     pub fn add_buffers(&mut self, buffers: Vec<(String, String, communication::Buffer)>) {
         for (plot_name, subplot_name, buffer) in buffers {
             let subplot_names = if subplot_name == plot_name {
@@ -39,6 +55,12 @@ impl Manager {
                     for (time, [x, y]) in v {
                         self.add_point(&plot_name, &subplot_names[0], [time.as_secs_f64(), x]);
                         self.add_point(&plot_name, &subplot_names[1], [time.as_secs_f64(), y]);
+                        self.add_vector(
+                            &plot_name,
+                            &subplot_name,
+                            (time.as_secs_f64(), [x, y, 0.0]),
+                            2,
+                        );
                     }
                 }
                 communication::Buffer::Vec3(v) => {
@@ -46,42 +68,121 @@ impl Manager {
                         self.add_point(&plot_name, &subplot_names[0], [time.as_secs_f64(), x]);
                         self.add_point(&plot_name, &subplot_names[1], [time.as_secs_f64(), y]);
                         self.add_point(&plot_name, &subplot_names[2], [time.as_secs_f64(), z]);
+                        self.add_vector(
+                            &plot_name,
+                            &subplot_name,
+                            (time.as_secs_f64(), [x, y, z]),
+                            3,
+                        );
                     }
                 }
             }
         }
     }
-    pub fn draw_menu(&mut self, ui: &mut Ui) {
-        for (name, graph) in &mut self.graphs {
-            if ui.button(name).clicked() {
-                graph.enabled = true;
-                break;
-            }
-        }
+    pub fn take_restarted(&mut self) -> bool {
+        std::mem::take(&mut self.restarted)
     }
-    pub fn draw_graphs(&mut self, ctx: &Context) {
-        for graph in self.graphs.values_mut() {
-            graph.draw(ctx);
+    // returns the name of the graph selected to be opened
+    pub fn draw_menu(&self, ui: &mut Ui) -> Option<String> {
+        self.graphs
+            .keys()
+            .find(|name| ui.button(*name).clicked())
+            .cloned()
+    }
+    // (graph, subplot) of every scalar series, sorted
+    pub fn series(&self) -> Vec<(String, String)> {
+        let mut series: Vec<(String, String)> = self
+            .graphs
+            .iter()
+            .flat_map(|(name, graph)| {
+                graph
+                    .subplots
+                    .keys()
+                    .map(|subplot| (name.clone(), subplot.clone()))
+            })
+            .collect();
+        series.sort();
+        series
+    }
+    pub fn points(&self, graph: &str, subplot: &str) -> Option<&[[f64; 2]]> {
+        self.graphs.get(graph)?.points(subplot)
+    }
+    pub fn graph_ui(&mut self, name: &str, ui: &mut Ui) {
+        match self.graphs.get_mut(name) {
+            Some(graph) => graph.ui(ui),
+            None => {
+                ui.label("Waiting for data");
+            }
         }
     }
 }
 
 #[derive(Debug)]
 pub struct Graph {
-    enabled: bool,
     name: String,
     subplots: HashMap<String, Vec<[f64; 2]>>,
+    // Vec2/Vec3 buffers kept whole for plotting one component against another
+    vectors: HashMap<String, Trajectory>,
+    mode: Mode,
+    xy: XyOptions,
+    step: StepAnalysis,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+enum Mode {
+    #[default]
+    Time,
+    Xy,
+}
+
+#[derive(Debug)]
+struct Trajectory {
+    dims: usize,
+    points: Vec<(f64, [f64; 3])>,
+}
+
+#[derive(Debug)]
+struct XyOptions {
+    vector: String,
+    // indices of the components plotted on the horizontal and vertical axes
+    axes: [usize; 2],
+    equal_aspect: bool,
+}
+
+impl Default for XyOptions {
+    fn default() -> Self {
+        Self {
+            vector: String::new(),
+            axes: [0, 1],
+            equal_aspect: true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct StepAnalysis {
+    enabled: bool,
+    setpoint: String,
+    measurement: String,
+    range: [f64; 2],
+    // x range of the plot last frame, used to pick the range from the view
+    visible: [f64; 2],
+    result: Option<StepResponse>,
 }
 
 impl Graph {
     pub fn new(name: String) -> Self {
         Self {
-            enabled: false,
             name,
             subplots: HashMap::new(),
+            vectors: HashMap::new(),
+            mode: Mode::default(),
+            xy: XyOptions::default(),
+            step: StepAnalysis::default(),
         }
     }
-    pub fn add_point(&mut self, subplot_name: &str, point: [f64; 2]) {
+    // returns true if the point caused the graph to be reset
+    pub fn add_point(&mut self, subplot_name: &str, point: [f64; 2]) -> bool {
         match self.subplots.get_mut(subplot_name) {
             Some(vec) => {
                 assert!(!vec.is_empty());
@@ -91,7 +192,9 @@ impl Graph {
                         log::warn!("Detected point with time before last point. Robot has probably been restarted: resetting plot.");
                         return {
                             self.subplots.clear();
+                            self.vectors.clear();
                             self.add_point(subplot_name, point);
+                            true
                         };
                     } else if point[0] - last[0] > 0.1 {
                         log::warn!("Detected time skip ({:.1}s to {:.1}s)", last[0], point[0]);
@@ -103,28 +206,264 @@ impl Graph {
                 self.subplots.insert(subplot_name.to_owned(), vec![point]);
             }
         }
+        false
     }
-    pub fn draw(&mut self, ctx: &Context) {
-        Window::new(format!("graph: {}", self.name))
-            .resizable(true)
-            .vscroll(true)
-            .open(&mut self.enabled)
-            .show(ctx, |ui| {
-                let mut lines = Vec::new();
-                let mut legend = self.subplots.len() != 1;
-                for (name, subplot_points) in &self.subplots {
-                    legend |= !name.is_empty();
-                    lines.push(egui_plot::Line::new(subplot_points.clone()).name(name));
-                }
-                let mut plot = egui_plot::Plot::new(self.name.clone()).view_aspect(2.0);
-                if legend {
-                    plot = plot.legend(egui_plot::Legend::default());
+    pub fn clear(&mut self) {
+        self.subplots.clear();
+        self.vectors.clear();
+    }
+    pub fn points(&self, subplot: &str) -> Option<&[[f64; 2]]> {
+        self.subplots.get(subplot).map(Vec::as_slice)
+    }
+    fn add_vector(&mut self, name: &str, point: (f64, [f64; 3]), dims: usize) {
+        self.vectors
+            .entry(name.to_owned())
+            .or_insert_with(|| Trajectory {
+                dims,
+                points: Vec::new(),
+            })
+            .points
+            .push(point);
+    }
+    pub fn ui(&mut self, ui: &mut Ui) {
+        if !self.vectors.is_empty() {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.mode, Mode::Time, "Time");
+                ui.selectable_value(&mut self.mode, Mode::Xy, "XY");
+            });
+        }
+        if self.mode == Mode::Xy && !self.vectors.is_empty() {
+            self.draw_xy(ui);
+            return;
+        }
+
+        ui.checkbox(&mut self.step.enabled, "Step response analysis");
+        if self.step.enabled {
+            self.draw_step_controls(ui);
+        }
+
+        let mut lines = Vec::new();
+        let mut legend = self.subplots.len() != 1;
+        for (name, subplot_points) in &self.subplots {
+            legend |= !name.is_empty();
+            lines.push(egui_plot::Line::new(subplot_points.clone()).name(name));
+        }
+        let mut plot = egui_plot::Plot::new(self.name.clone()).view_aspect(2.0);
+        if legend {
+            plot = plot.legend(egui_plot::Legend::default());
+        }
+        let step = &self.step;
+        let resp = plot.show(ui, |plot_ui| {
+            for line in lines {
+                plot_ui.line(line);
+            }
+            if step.enabled {
+                step.draw_annotations(plot_ui);
+            }
+            plot_ui.plot_bounds()
+        });
+        self.step.visible = [resp.inner.min()[0], resp.inner.max()[0]];
+    }
+    fn draw_xy(&mut self, ui: &mut Ui) {
+        const AXES: [&str; 3] = ["x", "y", "z"];
+
+        let xy = &mut self.xy;
+        if !self.vectors.contains_key(&xy.vector) {
+            xy.vector = self.vectors.keys().min().cloned().unwrap_or_default();
+        }
+        let dims = self.vectors[&xy.vector].dims;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source((&self.name, "xy vector"))
+                .selected_text(xy.vector.as_str())
+                .show_ui(ui, |ui| {
+                    let mut names: Vec<&String> = self.vectors.keys().collect();
+                    names.sort();
+                    for name in names {
+                        ui.selectable_value(&mut xy.vector, name.clone(), name.as_str());
+                    }
+                });
+            if dims == 3 {
+                egui::ComboBox::from_id_source((&self.name, "xy projection"))
+                    .selected_text(format!("{}{}", AXES[xy.axes[0]], AXES[xy.axes[1]]))
+                    .show_ui(ui, |ui| {
+                        for axes in [[0, 1], [0, 2], [1, 2]] {
+                            let label = format!("{}{}", AXES[axes[0]], AXES[axes[1]]);
+                            ui.selectable_value(&mut xy.axes, axes, label);
+                        }
+                    });
+            } else {
+                xy.axes = [0, 1];
+            }
+            ui.checkbox(&mut xy.equal_aspect, "Equal aspect");
+        });
+
+        let trajectory = &self.vectors[&xy.vector];
+        let points: Vec<[f64; 2]> = trajectory
+            .points
+            .iter()
+            .map(|(_, p)| [p[xy.axes[0]], p[xy.axes[1]]])
+            .collect();
+
+        let mut plot = egui_plot::Plot::new((&self.name, "xy")).view_aspect(1.0);
+        if xy.equal_aspect {
+            plot = plot.data_aspect(1.0);
+        }
+        plot.show(ui, |plot_ui| {
+            // split the trajectory into segments coloured from oldest (blue) to newest (red)
+            const SEGMENTS: usize = 64;
+            let chunk = (points.len() / SEGMENTS).max(1);
+            let n = points.len().saturating_sub(1).div_ceil(chunk).max(1);
+            for (i, start) in (0..points.len().saturating_sub(1))
+                .step_by(chunk)
+                .enumerate()
+            {
+                let end = (start + chunk + 1).min(points.len());
+                let t = i as f32 / n as f32;
+                let colour =
+                    egui::Color32::from_rgb((255.0 * t) as u8, 64, (255.0 * (1.0 - t)) as u8);
+                plot_ui.line(
+                    egui_plot::Line::new(points[start..end].to_vec())
+                        .color(colour)
+                        .width(2.),
+                );
+            }
+            if let Some(last) = points.last() {
+                plot_ui.points(
+                    egui_plot::Points::new(*last)
+                        .radius(4.)
+                        .color(egui::Color32::RED),
+                );
+            }
+            if let (Some((start, _)), Some((end, _))) =
+                (trajectory.points.first(), trajectory.points.last())
+            {
+                plot_ui.text(
+                    egui_plot::Text::new(points[0].into(), format!("{start:.1}s"))
+                        .color(egui::Color32::LIGHT_BLUE),
+                );
+                plot_ui.text(
+                    egui_plot::Text::new((*points.last().unwrap()).into(), format!("{end:.1}s"))
+                        .color(egui::Color32::LIGHT_RED),
+                );
+            }
+        });
+    }
+    fn draw_step_controls(&mut self, ui: &mut Ui) {
+        let mut names: Vec<&String> = self.subplots.keys().collect();
+        names.sort();
+        let step = &mut self.step;
+        egui::Grid::new(format!("step controls {}", self.name))
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (label, selected) in [
+                    ("Setpoint", &mut step.setpoint),
+                    ("Measurement", &mut step.measurement),
+                ] {
+                    ui.label(label);
+                    egui::ComboBox::from_id_source((&self.name, label))
+                        .selected_text(selected.as_str())
+                        .show_ui(ui, |ui| {
+                            for name in &names {
+                                ui.selectable_value(selected, (*name).clone(), name.as_str());
+                            }
+                        });
+                    ui.end_row();
                 }
-                plot.show(ui, |plot_ui| {
-                    for line in lines {
-                        plot_ui.line(line);
+                ui.label("Range (s)");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut step.range[0]).speed(0.01));
+                    ui.add(egui::DragValue::new(&mut step.range[1]).speed(0.01));
+                    if ui.button("Use visible").clicked() {
+                        step.range = step.visible;
                     }
                 });
+                ui.end_row();
             });
+
+        step.result = match (
+            self.subplots.get(&step.setpoint),
+            self.subplots.get(&step.measurement),
+        ) {
+            (Some(setpoint), Some(measurement)) => {
+                StepResponse::analyse(setpoint, measurement, step.range[0], step.range[1])
+            }
+            _ => None,
+        };
+
+        let Some(res) = step.result else {
+            ui.label("Select a setpoint, measurement and a range containing samples");
+            return;
+        };
+        let fmt = |v: Option<f64>| v.map_or_else(|| "-".to_owned(), |v| format!("{v:.3}s"));
+        egui::Grid::new(format!("step results {}", self.name))
+            .striped(true)
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (label, value) in [
+                    ("Rise time", fmt(res.rise_time())),
+                    ("Overshoot", format!("{:.1}%", res.overshoot)),
+                    ("Settling time", fmt(res.settling_time())),
+                    (
+                        "Steady state error",
+                        format!("{:.4}", res.steady_state_error),
+                    ),
+                    ("RMS error", format!("{:.4}", res.rms_error)),
+                    ("Peak to peak", format!("{:.4}", res.peak_to_peak)),
+                ] {
+                    ui.label(label);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+impl StepAnalysis {
+    fn draw_annotations(&self, plot_ui: &mut egui_plot::PlotUi) {
+        use egui::Color32;
+        use egui_plot::{HLine, LineStyle, Points, Text, VLine};
+
+        for x in self.range {
+            plot_ui.vline(VLine::new(x).color(Color32::GRAY));
+        }
+        let Some(res) = self.result else {
+            return;
+        };
+
+        let band = res.settling_band();
+        plot_ui.hline(
+            HLine::new(res.setpoint)
+                .color(Color32::GOLD)
+                .name("setpoint"),
+        );
+        for y in [res.setpoint - band, res.setpoint + band] {
+            plot_ui.hline(
+                HLine::new(y)
+                    .color(Color32::GOLD)
+                    .style(LineStyle::dashed_loose()),
+            );
+        }
+        for (t, label) in [
+            (res.rise_start, "10%"),
+            (res.rise_end, "90%"),
+            (res.settled_at, "settled"),
+        ] {
+            if let Some(t) = t {
+                plot_ui.vline(
+                    VLine::new(t)
+                        .color(Color32::LIGHT_BLUE)
+                        .style(LineStyle::dashed_loose()),
+                );
+                plot_ui.text(Text::new([t, res.setpoint].into(), label).color(Color32::LIGHT_BLUE));
+            }
+        }
+        if res.overshoot > 0.0 {
+            plot_ui.points(Points::new(res.peak).radius(4.).color(Color32::RED));
+            plot_ui.text(
+                Text::new(res.peak.into(), format!("{:.1}%", res.overshoot))
+                    .color(Color32::RED)
+                    .anchor(egui::Align2::CENTER_BOTTOM),
+            );
+        }
     }
 }
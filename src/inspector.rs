@@ -0,0 +1,197 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    time::SystemTime,
+};
+
+use communication::packets::ToClient;
+use eframe::egui;
+use egui::{Color32, Context, Ui, Window};
+use ron::ser::PrettyConfig;
+
+use crate::{link, logging::Logging};
+
+// packets kept for inspection, older ones are dropped
+const CAPACITY: usize = 2_000;
+
+struct Captured {
+    received: SystemTime,
+    variant: &'static str,
+    size: u64,
+    payload: String,
+}
+
+// lists the raw packets received from the robot
+#[derive(Default)]
+pub struct Inspector {
+    pub open: bool,
+    packets: VecDeque<Captured>,
+    // packets dropped from the front, so the selection stays on the same packet
+    dropped: usize,
+    paused: bool,
+    // variants that aren't listed
+    hidden: BTreeSet<String>,
+    // packets received of each variant, counted even while closed or paused
+    counts: BTreeMap<String, u64>,
+    // variants the editor ignores and how many of each were received
+    unhandled: BTreeMap<String, u64>,
+    selected: Option<usize>,
+}
+
+impl Inspector {
+    pub fn record(&mut self, pkts: &[ToClient]) {
+        if pkts.is_empty() {
+            return;
+        }
+        let received = SystemTime::now();
+        for pkt in pkts {
+            let variant = link::variant(pkt);
+            *self.counts.entry(variant.to_owned()).or_default() += 1;
+            // formatting every packet is only worth it while someone is looking
+            if !self.open || self.paused {
+                continue;
+            }
+            self.packets.push_back(Captured {
+                received,
+                variant,
+                size: link::size(pkt),
+                payload: ron::ser::to_string_pretty(pkt, PrettyConfig::default())
+                    .unwrap_or_else(|e| format!("Failed to format the packet: {e}")),
+            });
+        }
+        let excess = self.packets.len().saturating_sub(CAPACITY);
+        self.packets.drain(..excess);
+        self.dropped += excess;
+    }
+    pub fn record_unhandled(&mut self, pkt: &ToClient) {
+        let variant = link::variant(pkt);
+        let count = self.unhandled.entry(variant.to_owned()).or_default();
+        if *count == 0 {
+            log::warn!("Ignoring {variant} packets from the robot, see the packet inspector");
+        }
+        *count += 1;
+    }
+    // number of packets received that the editor ignored
+    pub fn unhandled(&self) -> u64 {
+        self.unhandled.values().sum()
+    }
+    pub fn draw(&mut self, ctx: &Context) {
+        let mut open = self.open;
+        Window::new("Packet inspector")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(450.0)
+            .show(ctx, |ui| self.ui(ui));
+        self.open = open;
+    }
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let label = if self.paused {
+                "▶ Resume"
+            } else {
+                "⏸ Pause"
+            };
+            if ui.button(label).clicked() {
+                self.paused = !self.paused;
+            }
+            if ui.button("Clear").clicked() {
+                self.dropped += self.packets.len();
+                self.packets.clear();
+                self.selected = None;
+            }
+            ui.label(format!("{} packets", self.packets.len()));
+            ui.separator();
+            let text = format!("{} unhandled", self.unhandled());
+            if self.unhandled.is_empty() {
+                ui.weak(text);
+            } else {
+                let variants = self
+                    .unhandled
+                    .iter()
+                    .map(|(variant, count)| format!("{variant}: {count}"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.colored_label(Color32::YELLOW, text)
+                    .on_hover_text(variants);
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Show");
+            for (variant, count) in &self.counts {
+                let mut shown = !self.hidden.contains(variant);
+                let mut text = egui::RichText::new(format!("{variant} ({count})"));
+                if self.unhandled.contains_key(variant) {
+                    text = text.color(Color32::YELLOW);
+                }
+                if ui.checkbox(&mut shown, text).changed() {
+                    if shown {
+                        self.hidden.remove(variant);
+                    } else {
+                        self.hidden.insert(variant.clone());
+                    }
+                }
+            }
+        });
+        ui.separator();
+
+        let rows: Vec<usize> = self
+            .packets
+            .iter()
+            .enumerate()
+            .filter(|(_, pkt)| !self.hidden.contains(pkt.variant))
+            .map(|(i, _)| i)
+            .collect();
+        let selected = self
+            .selected
+            .and_then(|id| id.checked_sub(self.dropped))
+            .filter(|i| *i < self.packets.len());
+        // leave room for the payload of the selected packet
+        let max_height = match selected {
+            Some(_) => ui.available_height() * 0.5,
+            None => f32::INFINITY,
+        };
+
+        // only the visible rows are laid out so rows have to be a single line
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .id_source("inspector packets")
+            .auto_shrink([false; 2])
+            .max_height(max_height)
+            .stick_to_bottom(!self.paused)
+            .show_rows(ui, row_height, rows.len(), |ui, range| {
+                egui::Grid::new("inspector packets")
+                    .striped(true)
+                    .num_columns(3)
+                    .start_row(range.start)
+                    .show(ui, |ui| {
+                        for &i in &rows[range] {
+                            let pkt = &self.packets[i];
+                            let id = self.dropped + i;
+                            let time = Logging::format_timestamp(
+                                pkt.received,
+                                time::macros::format_description!(
+                                    "[hour]:[minute]:[second].[subsecond digits:3]"
+                                ),
+                            );
+                            if ui
+                                .selectable_label(self.selected == Some(id), time)
+                                .clicked()
+                            {
+                                self.selected = Some(id);
+                            }
+                            ui.label(pkt.variant);
+                            ui.label(format!("≈{} B", pkt.size))
+                                .on_hover_text("Estimated from the packet contents");
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(i) = selected {
+            ui.separator();
+            egui::ScrollArea::both()
+                .id_source("inspector payload")
+                .auto_shrink([false; 2])
+                .show(ui, |ui| ui.monospace(&self.packets[i].payload));
+        }
+    }
+}
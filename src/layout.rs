@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+use eframe::egui;
+use egui::{Ui, WidgetText};
+use egui_dock::{DockArea, DockState, NodeIndex, Style, TabViewer};
+use serde::{Deserialize, Serialize};
+
+use communication::packets::ToRobot;
+
+use crate::{
+    characterize::Characterize, drift::Drift, drive_sim::DriveSim, graph, logging::Logging,
+    pid::Pid, plot::Plot, routine::Routine,
+};
+
+const STORAGE_KEY: &str = "layout";
+const DEFAULT_PRESET: &str = "Match";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Tab {
+    Field,
+    Logs,
+    Pid,
+    Graph(String),
+    Characterize,
+    Routine,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Layout {
+    dock: DockState<Tab>,
+    presets: BTreeMap<String, DockState<Tab>>,
+    #[serde(skip)]
+    new_preset: String,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        let presets = Self::default_presets();
+        Self {
+            dock: presets[DEFAULT_PRESET].clone(),
+            presets,
+            new_preset: String::new(),
+        }
+    }
+}
+
+// borrows everything that can be shown in a tab for the duration of a frame
+pub struct Panels<'a> {
+    pub plot: &'a mut Plot,
+    pub logging: &'a mut Logging,
+    pub graphing: &'a mut graph::Manager,
+    pub pid: &'a mut Pid,
+    pub characterize: &'a mut Characterize,
+    pub routine: &'a mut Routine,
+    pub drive_sim: &'a mut DriveSim,
+    pub drift: &'a mut Drift,
+    // packet for the robot from the pid tab
+    pub pid_packet: Option<ToRobot>,
+}
+
+impl Layout {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+    fn default_presets() -> BTreeMap<String, DockState<Tab>> {
+        let mut presets = BTreeMap::new();
+
+        let mut tuning = DockState::new(vec![Tab::Field]);
+        let [_, right] =
+            tuning
+                .main_surface_mut()
+                .split_right(NodeIndex::root(), 0.5, vec![Tab::Pid]);
+        tuning
+            .main_surface_mut()
+            .split_below(right, 0.4, vec![Tab::Logs]);
+        presets.insert("Tuning".to_owned(), tuning);
+
+        let mut match_ = DockState::new(vec![Tab::Field]);
+        match_
+            .main_surface_mut()
+            .split_right(NodeIndex::root(), 0.65, vec![Tab::Logs]);
+        presets.insert("Match".to_owned(), match_);
+
+        let mut debug = DockState::new(vec![Tab::Field]);
+        debug
+            .main_surface_mut()
+            .split_below(NodeIndex::root(), 0.6, vec![Tab::Logs, Tab::Pid]);
+        presets.insert("Debug".to_owned(), debug);
+
+        presets
+    }
+    // focus the tab if it is already open otherwise add it to the focused split
+    pub fn open(&mut self, tab: Tab) {
+        if self.dock.find_tab(&tab).is_none() {
+            self.dock.push_to_focused_leaf(tab);
+        }
+    }
+    pub fn draw_menu(&mut self, ui: &mut Ui) {
+        let mut remove = None;
+        for (name, preset) in &self.presets {
+            ui.horizontal(|ui| {
+                if ui.button(name).clicked() {
+                    self.dock = preset.clone();
+                    ui.close_menu();
+                }
+                if ui
+                    .small_button("🗑")
+                    .on_hover_text("Delete preset")
+                    .clicked()
+                {
+                    remove = Some(name.clone());
+                }
+            });
+        }
+        if let Some(name) = remove {
+            self.presets.remove(&name);
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_preset);
+            if ui.button("Save as preset").clicked() && !self.new_preset.is_empty() {
+                self.presets
+                    .insert(std::mem::take(&mut self.new_preset), self.dock.clone());
+            }
+        });
+        if ui.button("Restore default presets").clicked() {
+            self.presets.extend(Self::default_presets());
+        }
+    }
+    pub fn draw(&mut self, ctx: &egui::Context, panels: &mut Panels) {
+        DockArea::new(&mut self.dock)
+            .style(Style::from_egui(ctx.style().as_ref()))
+            .show(ctx, panels);
+    }
+}
+
+impl TabViewer for Panels<'_> {
+    type Tab = Tab;
+
+    fn title(&mut self, tab: &mut Tab) -> WidgetText {
+        match tab {
+            Tab::Field => "Field".into(),
+            Tab::Logs => "Logs".into(),
+            Tab::Pid => "PID".into(),
+            Tab::Graph(name) => format!("graph: {name}").into(),
+            Tab::Characterize => "Characterization".into(),
+            Tab::Routine => "Routine".into(),
+        }
+    }
+    fn closeable(&mut self, tab: &mut Tab) -> bool {
+        // the field is the main view so it always stays somewhere in the layout
+        *tab != Tab::Field
+    }
+    fn ui(&mut self, ui: &mut Ui, tab: &mut Tab) {
+        match tab {
+            Tab::Field => self.plot.ui(ui),
+            Tab::Logs => self.logging.ui(ui),
+            Tab::Pid => {
+                if let Some(packet) = self.pid.ui(ui) {
+                    self.pid_packet = Some(packet);
+                }
+            }
+            Tab::Graph(name) => self.graphing.graph_ui(name, ui),
+            Tab::Characterize => {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.characterize.ui(ui, self.graphing);
+                });
+            }
+            Tab::Routine => {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if self.routine.ui(ui) {
+                        self.drive_sim.reset();
+                    }
+                    egui::CollapsingHeader::new("Simulation")
+                        .default_open(true)
+                        .show(ui, |ui| self.drive_sim.ui(ui, &self.routine.actions));
+                    egui::CollapsingHeader::new("Drift")
+                        .show(ui, |ui| self.drift.ui(ui, &self.routine.actions));
+                });
+            }
+        }
+    }
+}
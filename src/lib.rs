@@ -0,0 +1,31 @@
+use eframe::egui;
+
+pub mod analysis;
+pub mod autotune;
+pub mod capture;
+pub mod characterize;
+pub mod connection;
+pub mod drift;
+pub mod drive_sim;
+pub mod files;
+pub mod graph;
+pub mod help;
+pub mod inspector;
+pub mod layout;
+pub mod link;
+pub mod log_file;
+pub mod logging;
+pub mod mock;
+pub mod pid;
+pub mod plot;
+pub mod recorder;
+pub mod replay;
+pub mod rng;
+pub mod robot;
+pub mod routine;
+pub mod session_file;
+pub mod simulation;
+pub mod timeline;
+pub mod tools;
+pub mod transport;
+pub mod vec;
@@ -0,0 +1,207 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant, SystemTime},
+};
+
+use communication::{packets::ToClient, Buffer};
+use eframe::egui;
+use egui::Ui;
+use egui_plot::{Legend, Line, Plot};
+
+use crate::logging::{Logging, TIME_FORMAT};
+
+// seconds of per second totals kept for the history graph
+const HISTORY_LEN: usize = 120;
+// packets further apart than this while connected are reported as a gap
+const GAP_THRESHOLD: Duration = Duration::from_millis(300);
+const GAPS_KEPT: usize = 20;
+
+pub fn variant(pkt: &ToClient) -> &'static str {
+    match pkt {
+        ToClient::Log(_) => "Log",
+        ToClient::PointBuffer(..) => "PointBuffer",
+        ToClient::Odometry(..) => "Odometry",
+        _ => "Other",
+    }
+}
+
+// rough size of the packet on the wire from the strings and points that make
+// up most of it, the transport doesn't report what it actually received
+pub fn size(pkt: &ToClient) -> u64 {
+    // a timestamp and a float in messagepack, plus the framing of a packet
+    const TIME: usize = 12;
+    const FLOAT: usize = 9;
+    const HEADER: usize = 4;
+    let body = match pkt {
+        ToClient::Log(log) => TIME + log.msg.len(),
+        ToClient::PointBuffer(plot, subplot, buffer) => {
+            plot.len()
+                + subplot.len()
+                + match buffer {
+                    Buffer::Scalar(v) => v.len() * (TIME + FLOAT),
+                    Buffer::Vec2(v) => v.len() * (TIME + 2 * FLOAT),
+                    Buffer::Vec3(v) => v.len() * (TIME + 3 * FLOAT),
+                }
+        }
+        ToClient::Odometry(name, ..) => name.len() + 5 * FLOAT,
+        _ => 0,
+    };
+    (HEADER + body) as u64
+}
+
+#[derive(Default, Clone)]
+struct Bucket {
+    // variant -> (packets, bytes)
+    variants: BTreeMap<&'static str, (u64, u64)>,
+}
+
+impl Bucket {
+    fn totals(&self) -> (u64, u64) {
+        self.variants
+            .values()
+            .fold((0, 0), |(p, b), (packets, bytes)| (p + packets, b + bytes))
+    }
+}
+
+// traffic statistics for the current connection
+pub struct Link {
+    start: Instant,
+    bucket_start: Instant,
+    current: Bucket,
+    // completed seconds, oldest first
+    history: VecDeque<(f64, Bucket)>,
+    last_packet: Option<Instant>,
+    gaps: VecDeque<(SystemTime, Duration)>,
+    gap_count: usize,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            bucket_start: now,
+            current: Bucket::default(),
+            history: VecDeque::new(),
+            last_packet: None,
+            gaps: VecDeque::new(),
+            gap_count: 0,
+        }
+    }
+}
+
+impl Link {
+    pub fn record(&mut self, pkts: &[ToClient]) {
+        let now = Instant::now();
+        while now - self.bucket_start >= Duration::from_secs(1) {
+            let secs = (self.bucket_start - self.start).as_secs_f64();
+            self.history
+                .push_back((secs, std::mem::take(&mut self.current)));
+            if self.history.len() > HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.bucket_start += Duration::from_secs(1);
+        }
+        if pkts.is_empty() {
+            return;
+        }
+
+        if let Some(last) = self.last_packet {
+            let gap = now - last;
+            if gap > GAP_THRESHOLD {
+                log::debug!("No packets for {:.0}ms", gap.as_secs_f64() * 1000.0);
+                self.gap_count += 1;
+                self.gaps.push_front((SystemTime::now(), gap));
+                self.gaps.truncate(GAPS_KEPT);
+            }
+        }
+        self.last_packet = Some(now);
+        for pkt in pkts {
+            let entry = self.current.variants.entry(variant(pkt)).or_default();
+            entry.0 += 1;
+            entry.1 += size(pkt);
+        }
+    }
+    pub fn ui(&mut self, ui: &mut Ui) {
+        // arrival times are only as precise as the frame rate
+        ui.ctx().request_repaint_after(Duration::from_millis(50));
+
+        let last = self
+            .history
+            .back()
+            .map(|(_, b)| b.clone())
+            .unwrap_or_default();
+        let (packets, bytes) = last.totals();
+        egui::Grid::new("link summary")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Gaps");
+                ui.label(format!(
+                    "{} over {}ms",
+                    self.gap_count,
+                    GAP_THRESHOLD.as_millis()
+                ));
+                ui.end_row();
+            });
+
+        egui::Grid::new("link variants")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("variant");
+                ui.strong("packets/s");
+                ui.strong("≈ bytes/s").on_hover_text(
+                    "Estimated from the packet contents, the link doesn't report bytes received",
+                );
+                ui.end_row();
+                for (name, (p, b)) in &last.variants {
+                    ui.label(name);
+                    ui.label(p.to_string());
+                    ui.label(format_bytes(*b));
+                    ui.end_row();
+                }
+                ui.strong("total");
+                ui.strong(packets.to_string());
+                ui.strong(format_bytes(bytes));
+                ui.end_row();
+            });
+
+        let series = |f: &dyn Fn(&Bucket) -> Option<f64>| -> Vec<[f64; 2]> {
+            self.history
+                .iter()
+                .filter_map(|(t, b)| Some([*t, f(b)?]))
+                .collect()
+        };
+        let packets = series(&|b| Some(b.totals().0 as f64));
+        let kilobytes = series(&|b| Some(b.totals().1 as f64 / 1024.0));
+        Plot::new("link history")
+            .view_aspect(2.5)
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(packets).name("packets/s"));
+                plot_ui.line(Line::new(kilobytes).name("≈ KiB/s"));
+            });
+
+        if !self.gaps.is_empty() {
+            egui::CollapsingHeader::new("Recent gaps").show(ui, |ui| {
+                for (at, gap) in &self.gaps {
+                    ui.label(format!(
+                        "{} {:.0}ms",
+                        Logging::format_timestamp(*at, TIME_FORMAT),
+                        gap.as_secs_f64() * 1000.0
+                    ));
+                }
+            });
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{bytes} B")
+    }
+}
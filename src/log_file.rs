@@ -0,0 +1,193 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    files,
+    logging::{Entry, Source},
+};
+
+// start a new file once the current one grows past this size
+const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024;
+// delete the oldest files once the log directory grows past this size
+const MAX_DIR_SIZE: u64 = 256 * 1024 * 1024;
+
+// writes logs to `<data dir>/logs/<session start>-<part>.log` as one tab separated line per log:
+// <milliseconds since unix epoch>	<level>	<source>	<escaped message>
+// where source is `robot` or `app:<module>`
+pub struct LogFile {
+    session: String,
+    part: u32,
+    file: BufWriter<File>,
+    size: u64,
+}
+
+impl LogFile {
+    pub fn dir() -> PathBuf {
+        files::data_dir("logs")
+    }
+    pub fn create() -> io::Result<Self> {
+        fs::create_dir_all(Self::dir())?;
+        let session = time::OffsetDateTime::from(SystemTime::now())
+            .format(time::macros::format_description!(
+                "[year]-[month]-[day]_[hour]-[minute]-[second]"
+            ))
+            .unwrap();
+        let file = Self::open(&session, 0)?;
+        Ok(Self {
+            session,
+            part: 0,
+            file,
+            size: 0,
+        })
+    }
+    fn open(session: &str, part: u32) -> io::Result<BufWriter<File>> {
+        // padded so the parts of a session sort in order
+        let path = Self::dir().join(format!("{session}-{part:04}.log"));
+        Ok(BufWriter::new(File::create(path)?))
+    }
+    pub fn write(&mut self, entry: &Entry) -> io::Result<()> {
+        let millis = entry
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let source = match &entry.source {
+            Source::Robot => "robot".to_owned(),
+            Source::App(module) => format!("app:{module}"),
+        };
+        let line = format!(
+            "{millis}\t{}\t{source}\t{}\n",
+            entry.level,
+            escape(&entry.msg)
+        );
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+
+        if self.size > MAX_FILE_SIZE {
+            self.file.flush()?;
+            self.part += 1;
+            self.file = Self::open(&self.session, self.part)?;
+            self.size = 0;
+            Self::enforce_size_cap()?;
+        }
+        Ok(())
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+    fn enforce_size_cap() -> io::Result<()> {
+        let mut files = Self::log_files()?;
+        let mut total: u64 = files.iter().map(|(_, size)| size).sum();
+        // file names start with the date so sorted order is oldest first
+        files.reverse();
+        while total > MAX_DIR_SIZE {
+            let Some((path, size)) = files.pop() else {
+                break;
+            };
+            fs::remove_file(path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+    // all log files with their size, oldest first
+    fn log_files() -> io::Result<Vec<(PathBuf, u64)>> {
+        let mut files = files::list(&Self::dir(), "log")?;
+        // parts written before they were padded only sort by number
+        files.sort_by_cached_key(|(path, _)| split_name(path));
+        Ok(files)
+    }
+    // the files of each session in order, oldest session first
+    pub fn sessions() -> io::Result<Vec<(String, Vec<PathBuf>)>> {
+        let mut sessions: Vec<(String, Vec<PathBuf>)> = Vec::new();
+        for (path, _) in Self::log_files()? {
+            let (session, _) = split_name(&path);
+            match sessions.last_mut() {
+                Some((last, parts)) if *last == session => parts.push(path),
+                _ => sessions.push((session, vec![path])),
+            }
+        }
+        Ok(sessions)
+    }
+    // the logs of all parts of a session
+    pub fn read_session(parts: &[PathBuf]) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for path in parts {
+            entries.extend(Self::read(path)?);
+        }
+        Ok(entries)
+    }
+    pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let mut parts = line.splitn(4, '\t');
+            let (Some(millis), Some(level), Some(source), Some(msg)) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                log::warn!("Skipping malformed line in {}: {line}", path.display());
+                continue;
+            };
+            let source = match source.strip_prefix("app:") {
+                Some(module) => Source::App(module.to_owned()),
+                None => Source::Robot,
+            };
+            let (Ok(millis), Ok(level)) = (millis.parse(), level.parse()) else {
+                log::warn!("Skipping malformed line in {}: {line}", path.display());
+                continue;
+            };
+            entries.push(Entry {
+                level,
+                msg: unescape(msg),
+                timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+                repeats: 1,
+                source,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+impl Drop for LogFile {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+// session and part of a log file's name
+fn split_name(path: &Path) -> (String, u32) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    if let Some((session, part)) = stem.rsplit_once('-') {
+        if let Ok(part) = part.parse() {
+            return (session.to_owned(), part);
+        }
+    }
+    (stem.into_owned(), 0)
+}
+
+fn escape(msg: &str) -> String {
+    msg.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+fn unescape(msg: &str) -> String {
+    let mut out = String::with_capacity(msg.len());
+    let mut chars = msg.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -1,52 +1,480 @@
-use crate::egui::Label;
+use crate::{egui::Label, log_file::LogFile};
 use communication::packets::Log;
 use eframe::egui;
-use egui::{containers::Window, Context};
-use std::time::SystemTime;
+use egui::text::{LayoutJob, TextFormat};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::SystemTime};
+use time::format_description::FormatItem;
 
-#[derive(Debug, Default)]
+const STORAGE_KEY: &str = "log_filter";
+// time of day, as logs are shown
+pub(crate) const TIME_FORMAT: &[FormatItem<'_>] =
+    time::macros::format_description!("[hour]:[minute]:[second]");
+const DEFAULT_CAP: usize = 20_000;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub level: log::Level,
+    pub msg: String,
+    pub timestamp: SystemTime,
+    // number of consecutive identical messages collapsed into this entry
+    pub repeats: u32,
+    pub source: Source,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Robot,
+    // the editor itself, with the module the record came from
+    App(String),
+}
+
+impl From<Log> for Entry {
+    fn from(log: Log) -> Self {
+        Self {
+            level: log.level.to_string().parse().unwrap_or(log::Level::Info),
+            msg: log.msg,
+            timestamp: log.timestamp,
+            repeats: 1,
+            source: Source::Robot,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct Filter {
+    // indexed by `log::Level as usize - 1`
+    levels: [bool; 5],
+    robot: bool,
+    app: bool,
+    search: String,
+    regex: bool,
+    follow: bool,
+    since_restart: bool,
+    // number of logs kept in memory, older logs are only kept in the log file
+    cap: usize,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            levels: [true; 5],
+            robot: true,
+            app: true,
+            search: String::new(),
+            regex: false,
+            follow: true,
+            since_restart: false,
+            cap: DEFAULT_CAP,
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct Logging {
-    pub window: bool,
-    logs: Vec<Log>,
+    logs: Vec<Entry>,
+    filter: Filter,
+    // index of the first log after the robot was last restarted
+    restart: usize,
+    last_robot_timestamp: Option<SystemTime>,
+    // number of logs shown when paused
+    paused: Option<usize>,
+    // compiled search pattern and the search string and regex option it was
+    // compiled from
+    pattern: Option<(String, bool, Result<Regex, regex::Error>)>,
+    // file this session's logs are written to, None until the first log arrives
+    // or if writing has failed
+    file: Option<LogFile>,
+    file_failed: bool,
+    // past session being viewed instead of the live logs
+    archive: Option<Archive>,
+    // number of logs dropped from memory because of the cap
+    spilled: usize,
+    // indices of the logs passing the filter, cached between frames
+    rows: Option<Rows>,
+    // replayed logs come from a recording so aren't written to the log file
+    replaying: bool,
+}
+
+struct Rows {
+    key: RowsKey,
+    indices: Vec<usize>,
+    // logs before this index have already been filtered
+    scanned: usize,
+}
+
+#[derive(PartialEq)]
+struct RowsKey {
+    levels: [bool; 5],
+    robot: bool,
+    app: bool,
+    search: String,
+    regex: bool,
+    start: usize,
+    archive: bool,
+}
+
+struct Archive {
+    session: String,
+    logs: Vec<Entry>,
+    restart: usize,
 }
 
 impl Logging {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        Self {
+            filter: storage
+                .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, &self.filter);
+    }
     pub fn add_logs(&mut self, logs: Vec<Log>) {
-        self.logs.extend(logs);
+        self.add_entries(logs.into_iter().map(Entry::from));
+    }
+    pub fn add_entries(&mut self, entries: impl IntoIterator<Item = Entry>) {
+        let mut entries = entries.into_iter().peekable();
+        if entries.peek().is_none() {
+            return;
+        }
+        for entry in entries {
+            self.write(&entry);
+            if entry.source == Source::Robot {
+                // the robot clock going backwards means it has been restarted
+                let restarted = self
+                    .last_robot_timestamp
+                    .is_some_and(|last| entry.timestamp < last);
+                self.last_robot_timestamp = Some(entry.timestamp);
+                if restarted {
+                    self.mark_restart();
+                    self.logs.push(entry);
+                    continue;
+                }
+            }
+            Self::push_collapsed(&mut self.logs, entry);
+        }
+        self.enforce_cap();
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.flush() {
+                log::warn!("Failed to write logs to disk: {e}");
+                self.file = None;
+                self.file_failed = true;
+            }
+        }
+    }
+    fn push_collapsed(logs: &mut Vec<Entry>, entry: Entry) {
+        if let Some(last) = logs.last_mut() {
+            if last.level == entry.level && last.msg == entry.msg && last.source == entry.source {
+                last.repeats += 1;
+                return;
+            }
+        }
+        logs.push(entry);
     }
-    pub fn draw(&mut self, ctx: &Context) {
-        let create_row = |ui: &mut egui::Ui, log: &Log| {
-            ui.add(Label::new(log.level.to_string()).wrap(true));
-            ui.add(Label::new(log.msg.clone()).wrap(true));
-            ui.add(Label::new(Self::format_timestamp(log.timestamp)).wrap(true));
+    fn enforce_cap(&mut self) {
+        // drop in batches so the logs aren't shifted every frame once full
+        let cap = self.filter.cap.max(1);
+        if self.logs.len() <= cap + cap / 10 {
+            return;
+        }
+        let n = self.logs.len() - cap;
+        self.logs.drain(..n);
+        self.spilled += n;
+        self.restart = self.restart.saturating_sub(n);
+        self.paused = self.paused.map(|p| p.saturating_sub(n));
+        if self.archive.is_none() {
+            self.rows = None;
+        }
+    }
+    fn write(&mut self, entry: &Entry) {
+        if self.replaying {
+            return;
+        }
+        if self.file.is_none() && !self.file_failed {
+            match LogFile::create() {
+                Ok(file) => self.file = Some(file),
+                Err(e) => {
+                    log::warn!("Failed to create log file: {e}");
+                    self.file_failed = true;
+                }
+            }
+        }
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.write(entry) {
+                log::warn!("Failed to write logs to disk: {e}");
+                self.file = None;
+                self.file_failed = true;
+            }
+        }
+    }
+    pub fn open_session(&mut self, session: &str, parts: &[PathBuf]) {
+        match LogFile::read_session(parts) {
+            Ok(lines) => {
+                let mut logs = Vec::new();
+                for entry in lines {
+                    Self::push_collapsed(&mut logs, entry);
+                }
+                let robot: Vec<(usize, SystemTime)> = logs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, log)| log.source == Source::Robot)
+                    .map(|(i, log)| (i, log.timestamp))
+                    .collect();
+                let restart = robot
+                    .windows(2)
+                    .rfind(|w| w[1].1 < w[0].1)
+                    .map_or(0, |w| w[1].0);
+                self.archive = Some(Archive {
+                    session: session.to_owned(),
+                    logs,
+                    restart,
+                });
+                // the cached rows may be of another archive
+                self.rows = None;
+            }
+            Err(e) => log::warn!("Failed to open log session {session}: {e}"),
+        }
+    }
+    pub fn draw_sessions_menu(&mut self, ui: &mut egui::Ui) -> bool {
+        let sessions = match LogFile::sessions() {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                ui.label(format!("No log sessions: {e}"));
+                return false;
+            }
+        };
+        // newest first
+        for (session, parts) in sessions.iter().rev() {
+            if ui.button(session).clicked() {
+                self.open_session(session, parts);
+                ui.close_menu();
+                return true;
+            }
+        }
+        false
+    }
+    // forget the live logs, used when the packet source changes
+    pub fn clear(&mut self) {
+        self.logs.clear();
+        self.restart = 0;
+        self.last_robot_timestamp = None;
+        self.paused = None;
+        self.spilled = 0;
+        self.rows = None;
+    }
+    pub fn set_replaying(&mut self, replaying: bool) {
+        self.replaying = replaying;
+        self.clear();
+    }
+    // the live logs, oldest first
+    pub fn entries(&self) -> &[Entry] {
+        &self.logs
+    }
+    // logs since the robot was last restarted
+    pub fn since_restart(&self) -> &[Entry] {
+        &self.logs[self.restart..]
+    }
+    pub fn mark_restart(&mut self) {
+        self.restart = self.logs.len();
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(archive) = &self.archive {
+            let mut close = false;
+            ui.horizontal(|ui| {
+                ui.label(format!("Viewing {} (read only)", archive.session));
+                close = ui.button("Back to live logs").clicked();
+            });
+            if close {
+                self.archive = None;
+            }
+        } else if self.spilled > 0 {
+            ui.weak(format!(
+                "{} older logs are only kept on disk (File > Open log session)",
+                self.spilled
+            ));
+        }
+        self.draw_filter(ui);
+        ui.separator();
+
+        self.update_pattern();
+        let matcher = Self::matcher(&self.filter, &self.pattern);
+        let (logs, restart, end) = match &self.archive {
+            Some(archive) => (&archive.logs, archive.restart, archive.logs.len()),
+            None => (
+                &self.logs,
+                self.restart,
+                self.paused.unwrap_or(self.logs.len()),
+            ),
+        };
+        let start = if self.filter.since_restart {
+            restart.min(end)
+        } else {
+            0
+        };
+        let filter = &self.filter;
+
+        let key = RowsKey {
+            levels: filter.levels,
+            robot: filter.robot,
+            app: filter.app,
+            search: filter.search.clone(),
+            regex: filter.regex,
+            start,
+            archive: self.archive.is_some(),
+        };
+        if !matches!(&self.rows, Some(rows) if rows.key == key && rows.scanned <= end) {
+            self.rows = Some(Rows {
+                key,
+                indices: Vec::new(),
+                scanned: start,
+            });
+        }
+        let rows = self.rows.as_mut().unwrap();
+        for (i, log) in logs.iter().enumerate().take(end).skip(rows.scanned) {
+            let level_shown = filter.levels[log.level as usize - 1];
+            let source_shown = match log.source {
+                Source::Robot => filter.robot,
+                Source::App(_) => filter.app,
+            };
+            if level_shown
+                && source_shown
+                && matcher.as_ref().map_or(true, |m| !m(&log.msg).is_empty())
+            {
+                rows.indices.push(i);
+            }
+        }
+        rows.scanned = end;
+
+        let create_row = |ui: &mut egui::Ui, log: &Entry| {
+            let matches = matcher.as_ref().map_or_else(Vec::new, |m| m(&log.msg));
+            ui.label(log.level.to_string());
+            match &log.source {
+                Source::Robot => ui.colored_label(egui::Color32::GOLD, "robot"),
+                Source::App(module) => ui
+                    .colored_label(egui::Color32::LIGHT_BLUE, "app")
+                    .on_hover_text(module),
+            };
+            ui.label(Self::format_timestamp(log.timestamp, TIME_FORMAT));
+            ui.horizontal(|ui| {
+                if log.repeats > 1 {
+                    ui.weak(format!("×{}", log.repeats));
+                }
+                ui.add(Label::new(Self::highlight(ui, &log.msg, &matches)).truncate(true))
+                    .on_hover_text(&log.msg);
+            });
             ui.end_row();
         };
 
-        Window::new("Logs")
-            .resizable(true)
-            .open(&mut self.window)
-            .show(ctx, |ui| {
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    egui::Grid::new("action help")
-                        .striped(true)
-                        .num_columns(3)
-                        .show(ui, |ui| {
-                            ui.heading("Level");
-                            ui.heading("Message");
-                            ui.heading("Time");
-                            ui.end_row();
-                            for log in &self.logs {
-                                create_row(ui, log);
-                            }
-                        });
-                });
+        // only the visible rows are laid out so rows have to be a single line
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .stick_to_bottom(filter.follow)
+            .show_rows(ui, row_height, rows.indices.len(), |ui, range| {
+                egui::Grid::new("logs")
+                    .striped(true)
+                    .num_columns(4)
+                    .start_row(range.start)
+                    .show(ui, |ui| {
+                        for &i in &rows.indices[range] {
+                            create_row(ui, &logs[i]);
+                        }
+                    });
             });
     }
-    fn format_timestamp(t: SystemTime) -> String {
+    fn draw_filter(&mut self, ui: &mut egui::Ui) {
+        let filter = &mut self.filter;
+        ui.horizontal_wrapped(|ui| {
+            for level in log::Level::iter() {
+                ui.checkbox(&mut filter.levels[level as usize - 1], level.as_str());
+            }
+            ui.separator();
+            ui.checkbox(&mut filter.robot, "Robot");
+            ui.checkbox(&mut filter.app, "App");
+            ui.separator();
+            ui.checkbox(&mut filter.follow, "Follow");
+            let mut paused = self.paused.is_some();
+            if ui.checkbox(&mut paused, "Pause").changed() {
+                self.paused = paused.then_some(self.logs.len());
+            }
+            ui.checkbox(&mut filter.since_restart, "Since last restart");
+            ui.separator();
+            ui.label("Keep in memory");
+            ui.add(egui::DragValue::new(&mut filter.cap).clamp_range(1_000..=1_000_000));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            ui.text_edit_singleline(&mut filter.search);
+            ui.checkbox(&mut filter.regex, "Regex");
+        });
+        if let Some((_, _, Err(e))) = &self.pattern {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+        }
+    }
+    // recompile the search regex if it has changed, plain searches are escaped so
+    // both are matched the same way
+    fn update_pattern(&mut self) {
+        let (search, regex) = (&self.filter.search, self.filter.regex);
+        if !matches!(&self.pattern, Some((s, r, _)) if s == search && *r == regex) {
+            let pattern = if regex {
+                search.clone()
+            } else {
+                regex::escape(search)
+            };
+            let compiled = RegexBuilder::new(&pattern).case_insensitive(true).build();
+            self.pattern = Some((search.clone(), regex, compiled));
+        }
+    }
+    // returns a function giving the byte ranges of the search matches in a message
+    // or None if there is no search active
+    fn matcher<'a>(
+        filter: &'a Filter,
+        pattern: &'a Option<(String, bool, Result<Regex, regex::Error>)>,
+    ) -> Option<Box<dyn Fn(&str) -> Vec<(usize, usize)> + 'a>> {
+        if filter.search.is_empty() {
+            return None;
+        }
+        match pattern {
+            Some((_, _, Ok(regex))) => Some(Box::new(move |msg: &str| {
+                regex
+                    .find_iter(msg)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (m.start(), m.end()))
+                    .collect()
+            })),
+            // an invalid pattern shows everything until it is fixed
+            _ => None,
+        }
+    }
+    fn highlight(ui: &egui::Ui, msg: &str, matches: &[(usize, usize)]) -> LayoutJob {
+        let normal = TextFormat {
+            font_id: egui::TextStyle::Body.resolve(ui.style()),
+            color: ui.visuals().text_color(),
+            ..Default::default()
+        };
+        let highlighted = TextFormat {
+            background: ui.visuals().selection.bg_fill,
+            ..normal.clone()
+        };
+
+        let mut job = LayoutJob::default();
+        let mut last = 0;
+        for &(start, end) in matches {
+            job.append(&msg[last..start], 0.0, normal.clone());
+            job.append(&msg[start..end], 0.0, highlighted.clone());
+            last = end;
+        }
+        job.append(&msg[last..], 0.0, normal);
+        job
+    }
+    pub(crate) fn format_timestamp(t: SystemTime, format: &[FormatItem]) -> String {
         <SystemTime as Into<time::OffsetDateTime>>::into(t)
-            .format(time::macros::format_description!(
-                "[hour]:[minute]:[second]"
-            ))
+            .format(format)
             .unwrap()
     }
 }
@@ -1,29 +1,36 @@
-use communication::{packets::*, ClientListener};
 use eframe::egui;
 use egui::Context;
+use std::sync::mpsc::Receiver;
 
-mod graph;
-mod help;
-mod logging;
-mod pid;
-mod plot;
-mod robot;
-mod tools;
-mod vec;
-
-use help::Help;
-use logging::Logging;
-use pid::Pid;
-use plot::Plot;
-use tools::{PointSelection, Tools};
+use path_gui_editor::{
+    capture,
+    characterize::Characterize,
+    connection::Connection,
+    drift::Drift,
+    drive_sim::{DriveSim, SIM_ROBOT},
+    files, graph,
+    help::Help,
+    inspector::Inspector,
+    layout::{Layout, Panels, Tab},
+    logging::{Entry, Logging},
+    mock::{self, Mock},
+    pid::Pid,
+    plot::Plot,
+    recorder::Recorder,
+    replay::Replay,
+    routine::Routine,
+    session_file,
+    tools::{PointSelection, Tools},
+    transport::Handlers,
+};
 
 fn main() {
-    env_logger::init();
+    let app_logs = capture::Capture::init();
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
-        "Path Editor",
+        files::APP_NAME,
         native_options,
-        Box::new(|cc| Box::new(App::new(cc))),
+        Box::new(|cc| Box::new(App::new(cc, app_logs))),
     )
     .unwrap();
 }
@@ -32,25 +39,44 @@ struct App {
     plot: Plot,
     help: Help,
     logging: Logging,
+    // records logged by the editor itself
+    app_logs: Receiver<Entry>,
     graphing: graph::Manager,
-    listener: ClientListener,
+    connection: Connection,
+    inspector: Inspector,
+    recorder: Recorder,
+    // recorded session shown instead of the live connection
+    replay: Option<Replay>,
+    // stand-in robot used instead of the live connection
+    mock: Option<Mock>,
     pid: Pid,
+    characterize: Characterize,
+    routine: Routine,
+    drive_sim: DriveSim,
+    drift: Drift,
+    layout: Layout,
 }
 
 impl App {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let listener = ClientListener::new(
-            "127.0.0.1:8733".parse().unwrap(),
-            ClientInfo::new(format!("{}", gethostname::gethostname().to_string_lossy())),
-        );
+    fn new(cc: &eframe::CreationContext<'_>, app_logs: Receiver<Entry>) -> Self {
         cc.egui_ctx.set_pixels_per_point(1.5);
         Self {
             help: Help::default(),
             plot: Plot::new(&cc.egui_ctx),
-            logging: Logging::default(),
+            logging: Logging::load(cc.storage),
+            app_logs,
             graphing: graph::Manager::default(),
-            listener,
-            pid: Pid::default(),
+            connection: Connection::load(cc.storage),
+            inspector: Inspector::default(),
+            recorder: Recorder::default(),
+            replay: None,
+            mock: None,
+            pid: Pid::load(cc.storage),
+            characterize: Characterize::default(),
+            routine: Routine::load(cc.storage),
+            drive_sim: DriveSim::load(cc.storage),
+            drift: Drift::load(cc.storage),
+            layout: Layout::load(cc.storage),
         }
     }
 
@@ -61,6 +87,34 @@ impl App {
                 egui::menu::bar(ui, |ui| {
                     ui.menu_button("File", |ui| {
                         ui.button("Save Path As (TODO)").clicked();
+                        ui.menu_button("Open log session", |ui| {
+                            if self.logging.draw_sessions_menu(ui) {
+                                self.layout.open(Tab::Logs);
+                            }
+                        });
+                        ui.menu_button("Replay recording", |ui| {
+                            if let Some(path) = session_file::draw_sessions_menu(ui) {
+                                match Replay::open(&path) {
+                                    Ok(replay) => {
+                                        self.logging.set_replaying(true);
+                                        self.replay = Some(replay);
+                                        self.mock = None;
+                                    }
+                                    Err(e) => {
+                                        log::warn!("Failed to open {}: {e}", path.display())
+                                    }
+                                }
+                            }
+                        });
+                        ui.menu_button("Mock robot", |ui| {
+                            if let Some(mock) = mock::draw_menu(ui) {
+                                self.mock = Some(mock);
+                                self.replay = None;
+                                self.logging.set_replaying(false);
+                            }
+                        });
+                        ui.separator();
+                        self.recorder.draw_menu(ui, self.connection.client_name());
                     });
                     ui.menu_button("Units", |ui| {
                         ui.checkbox(&mut true, "Use metric (TODO)");
@@ -78,17 +132,36 @@ impl App {
                                 selection: PointSelection::default(),
                             });
                         }
+                        ui.separator();
+                        if ui.button("Routine").clicked() {
+                            self.layout.open(Tab::Routine);
+                        }
                     });
                     ui.menu_button("Communication", |ui| {
                         if ui.button("logs").clicked() {
-                            self.logging.window = true;
+                            self.layout.open(Tab::Logs);
                         }
                         if ui.button("pid").clicked() {
-                            self.pid.window = true;
+                            self.layout.open(Tab::Pid);
+                        }
+                        if ui.button("connection").clicked() {
+                            self.connection.open = true;
+                        }
+                        if ui.button("packet inspector").clicked() {
+                            self.inspector.open = true;
                         }
                     });
                     ui.menu_button("Graphs", |ui| {
-                        self.graphing.draw_menu(ui);
+                        if let Some(name) = self.graphing.draw_menu(ui) {
+                            self.layout.open(Tab::Graph(name));
+                        }
+                        ui.separator();
+                        if ui.button("Feed-forward characterization").clicked() {
+                            self.layout.open(Tab::Characterize);
+                        }
+                    });
+                    ui.menu_button("Layout", |ui| {
+                        self.layout.draw_menu(ui);
                     });
                     ui.menu_button("Help", |ui| {
                         if ui.button("Actions").clicked() {
@@ -100,7 +173,11 @@ impl App {
                         if ui.button("About (TODO)").clicked() {
                             self.help.about = true;
                         }
-                    })
+                    });
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        self.connection.draw_status(ui);
+                        self.recorder.draw_status(ui);
+                    });
                 });
             });
     }
@@ -110,38 +187,73 @@ impl eframe::App for App {
     fn update(&mut self, ctx: &Context, _: &mut eframe::Frame) {
         // draw help
         self.help.draw(ctx);
+        self.connection.draw(ctx);
+        self.inspector.draw(ctx);
 
-        let pkts = self.listener.get_packets();
-
-        let mut logs = Vec::new();
-        let mut point_buffers = Vec::new();
-        for pkt in pkts {
-            match pkt {
-                ToClient::Log(l) => logs.push(l),
-                ToClient::PointBuffer(plt_name, subplt_name, buffer) => {
-                    point_buffers.push((plt_name, subplt_name, buffer));
-                }
-                ToClient::Odometry(name, dim, pos, rot) => {
-                    self.plot.set_odom(name, dim, pos, rot);
-                }
-                _ => {}
+        let mut handlers = Handlers {
+            plot: &mut self.plot,
+            logging: &mut self.logging,
+            graphing: &mut self.graphing,
+            pid: &mut self.pid,
+            recorder: Some(&mut self.recorder),
+            inspector: Some(&mut self.inspector),
+        };
+        match (&mut self.replay, &mut self.mock) {
+            (Some(replay), _) => {
+                // the live link is kept up while replaying but its packets are dropped
+                self.connection.get_packets();
+                handlers.recorder = None;
+                handlers.receive(replay);
+            }
+            (None, Some(mock)) => {
+                self.connection.get_packets();
+                handlers.receive(mock);
             }
+            (None, None) => handlers.receive(&mut self.connection),
         }
+        self.logging.add_entries(self.app_logs.try_iter());
 
-        if let Some(val) = self.pid.draw(ctx) {
-            //self.comms.send_packet(ToRobot::Pid(val));
+        self.plot
+            .set_routine(&self.routine.actions, self.drive_sim.drivetrain());
+        if self
+            .drift
+            .update(ctx, &self.routine.actions, self.drive_sim.drivetrain())
+        {
+            self.plot.set_drift(self.drift.spreads().to_vec());
+        }
+        match self.drive_sim.update(ctx, &self.routine.actions) {
+            Some(pose) => self.plot.set_odom(
+                SIM_ROBOT.to_owned(),
+                self.drive_sim.drivetrain().size,
+                pose.pos.into(),
+                pose.heading,
+            ),
+            None => self.plot.remove_odom(SIM_ROBOT),
         }
-
-        // draw logs
-        self.logging.add_logs(logs);
-        self.logging.draw(ctx);
-
-        // draw graphs
-        self.graphing.add_buffers(point_buffers);
-        self.graphing.draw_graphs(ctx);
 
         // top menu is fixed size of 30px tall
         self.draw_menu(ctx, 30.);
+        if let Some(replay) = &mut self.replay {
+            let keep = egui::TopBottomPanel::bottom("replay")
+                .show(ctx, |ui| replay.ui(ui))
+                .inner;
+            if !keep {
+                self.replay = None;
+                self.logging.set_replaying(false);
+                self.graphing = graph::Manager::default();
+                self.plot.clear_odom();
+            }
+        }
+        if let Some(mock) = &mut self.mock {
+            let keep = egui::TopBottomPanel::bottom("mock")
+                .show(ctx, |ui| mock.ui(ui))
+                .inner;
+            if !keep {
+                self.mock = None;
+                self.graphing = graph::Manager::default();
+                self.plot.clear_odom();
+            }
+        }
 
         // calculate sizing for left/bottom panel
         // which is the remaining size from having the
@@ -154,7 +266,33 @@ impl eframe::App for App {
             (1, win_size.y - win_size.x)
         };
 
-        // draw plot with the field and path and tools on it
-        self.plot.draw(ctx);
+        // draw the field, logs, graphs and pid in the docked layout
+        let mut panels = Panels {
+            plot: &mut self.plot,
+            logging: &mut self.logging,
+            graphing: &mut self.graphing,
+            pid: &mut self.pid,
+            characterize: &mut self.characterize,
+            routine: &mut self.routine,
+            drive_sim: &mut self.drive_sim,
+            drift: &mut self.drift,
+            pid_packet: None,
+        };
+        self.layout.draw(ctx, &mut panels);
+        if let Some(packet) = panels.pid_packet {
+            match &mut self.mock {
+                Some(mock) => mock.send_packet(packet),
+                None => self.connection.send_packet(packet),
+            }
+        }
+    }
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.layout.save(storage);
+        self.logging.save(storage);
+        self.pid.save(storage);
+        self.connection.save(storage);
+        self.routine.save(storage);
+        self.drive_sim.save(storage);
+        self.drift.save(storage);
     }
 }
@@ -0,0 +1,365 @@
+use std::{
+    f64::consts::PI,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use communication::{
+    packets::{Log, ToClient, ToRobot},
+    Buffer,
+};
+use eframe::egui;
+use egui::Ui;
+
+use crate::{files, rng::Rng};
+
+// stand-in robot so the editor can be used without hardware. the pinned
+// communication rev has no robot side of the protocol to serve it with, so it
+// runs inside the editor as another packet source instead
+//
+// without a script it drives a figure eight and emits noisy telemetry and the
+// odd log. a script has one event per line, prefixed by the time it is sent at
+// in seconds:
+//   <t> log <level> <message>
+//   <t> scalar <plot> <subplot> <value>
+//   <t> vec2 <plot> <subplot> <x> <y>
+//   <t> vec3 <plot> <subplot> <x> <y> <z>
+//   <t> odom <name> <x> <y> <heading>
+// blank lines and lines starting with # are ignored
+
+const EXTENSION: &str = "txt";
+// telemetry samples per second
+const RATE: u32 = 50;
+// ticks missed beyond this, e.g. while the window was hidden, are skipped
+const MAX_CATCH_UP: Duration = Duration::from_secs(1);
+const ROBOT_SIZE: [f64; 2] = [0.45, 0.45];
+// how often the gains are reported, on the same plot the real robot uses
+const GAINS_INTERVAL: Duration = Duration::from_millis(500);
+const GAINS_PLOT: &str = "pid";
+const MESSAGES: [&str; 5] = [
+    "Autonomous routine selected",
+    "Battery voltage low",
+    "Intake jammed, reversing",
+    "Lost IMU reading, using wheel odometry",
+    "Path step complete",
+];
+
+#[derive(Debug, Clone)]
+enum Event {
+    Log(log::Level, String),
+    Scalar(String, String, f64),
+    Vec2(String, String, [f64; 2]),
+    Vec3(String, String, [f64; 3]),
+    Odom(String, [f64; 2], f64),
+}
+
+struct Script {
+    name: String,
+    events: Vec<(Duration, Event)>,
+    next: usize,
+    // mock time the current run of the script started at
+    start: Duration,
+    repeat: bool,
+}
+
+pub struct Mock {
+    started: Instant,
+    // mock time of the next telemetry sample
+    next_tick: Duration,
+    rng: Rng,
+    gains: [f64; 3],
+    last_gains: Option<Duration>,
+    script: Option<Script>,
+    pending: Vec<ToClient>,
+    // the editor forgets the previous source's packets on the first read
+    reset: bool,
+}
+
+pub fn dir() -> PathBuf {
+    files::data_dir("mock scripts")
+}
+
+impl Mock {
+    pub fn random(seed: u64) -> Self {
+        Self {
+            started: Instant::now(),
+            next_tick: Duration::ZERO,
+            rng: Rng::new(seed),
+            gains: [1.0, 0.0, 0.0],
+            last_gains: None,
+            script: None,
+            pending: Vec::new(),
+            reset: true,
+        }
+    }
+    pub fn scripted(name: &str, text: &str) -> Result<Self, String> {
+        let events = parse_script(text)?;
+        let mut mock = Self::random(1);
+        mock.script = Some(Script {
+            name: name.to_owned(),
+            events,
+            next: 0,
+            start: Duration::ZERO,
+            repeat: false,
+        });
+        Ok(mock)
+    }
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        Self::scripted(&name, &text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+    pub fn get_packets(&mut self) -> Vec<ToClient> {
+        self.advance(self.started.elapsed())
+    }
+    pub fn take_reset(&mut self) -> bool {
+        std::mem::take(&mut self.reset)
+    }
+    // runs the mock up to `now` since it started and returns what it sent
+    pub fn advance(&mut self, now: Duration) -> Vec<ToClient> {
+        let dt = Duration::from_secs(1) / RATE;
+        if now > self.next_tick + MAX_CATCH_UP {
+            self.next_tick = now - MAX_CATCH_UP;
+        }
+        while self.next_tick <= now {
+            let t = self.next_tick;
+            self.report_gains(t);
+            if self.script.is_some() {
+                self.script_tick(t);
+            } else {
+                self.random_tick(t, dt.as_secs_f64());
+            }
+            self.next_tick += dt;
+        }
+        std::mem::take(&mut self.pending)
+    }
+    pub fn send_packet(&mut self, packet: ToRobot) {
+        match packet {
+            ToRobot::Pid((kp, ki, kd)) => {
+                self.gains = [kp, ki, kd];
+                // report straight away so the editor can acknowledge the update
+                self.last_gains = None;
+                self.log(
+                    log::Level::Info,
+                    format!("Gains set to kp {kp} ki {ki} kd {kd}"),
+                );
+            }
+            _ => log::info!("The mock robot ignores this packet"),
+        }
+    }
+    // false once the mock is stopped
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        let mut keep = true;
+        ui.horizontal(|ui| {
+            match &mut self.script {
+                Some(script) => {
+                    ui.label(format!("Mock robot running {}", script.name));
+                    ui.checkbox(&mut script.repeat, "Loop");
+                }
+                None => {
+                    ui.label("Mock robot sending random telemetry");
+                }
+            }
+            if ui.button("Back to live").clicked() {
+                keep = false;
+            }
+        });
+        keep
+    }
+    fn log(&mut self, level: log::Level, msg: String) {
+        self.pending.push(ToClient::Log(Log {
+            level,
+            msg,
+            timestamp: SystemTime::now(),
+        }));
+    }
+    fn send(&mut self, t: Duration, event: Event) {
+        let pkt = match event {
+            Event::Log(level, msg) => return self.log(level, msg),
+            Event::Scalar(plot, subplot, v) => {
+                ToClient::PointBuffer(plot, subplot, Buffer::Scalar(vec![(t, v)]))
+            }
+            Event::Vec2(plot, subplot, v) => {
+                ToClient::PointBuffer(plot, subplot, Buffer::Vec2(vec![(t, v)]))
+            }
+            Event::Vec3(plot, subplot, v) => {
+                ToClient::PointBuffer(plot, subplot, Buffer::Vec3(vec![(t, v)]))
+            }
+            Event::Odom(name, pos, heading) => ToClient::Odometry(name, ROBOT_SIZE, pos, heading),
+        };
+        self.pending.push(pkt);
+    }
+    fn report_gains(&mut self, t: Duration) {
+        if self
+            .last_gains
+            .is_some_and(|last| t - last < GAINS_INTERVAL)
+        {
+            return;
+        }
+        self.last_gains = Some(t);
+        for (term, value) in ["kp", "ki", "kd"].into_iter().zip(self.gains) {
+            self.send(
+                t,
+                Event::Scalar(GAINS_PLOT.to_owned(), term.to_owned(), value),
+            );
+        }
+    }
+    fn script_tick(&mut self, t: Duration) {
+        let Some(script) = &mut self.script else {
+            return;
+        };
+        let mut due = Vec::new();
+        while let Some((at, event)) = script.events.get(script.next) {
+            if script.start + *at > t {
+                break;
+            }
+            due.push(event.clone());
+            script.next += 1;
+        }
+        if script.next == script.events.len() && script.repeat {
+            script.next = 0;
+            script.start = t;
+        }
+        for event in due {
+            self.send(t, event);
+        }
+    }
+    // figure eight around the field centre with noisy sensors
+    fn random_tick(&mut self, at: Duration, dt: f64) {
+        let t = at.as_secs_f64();
+        let w = 2.0 * PI / 12.0;
+        let pos = [1.2 * (w * t).sin(), 0.6 * (2.0 * w * t).sin()];
+        let vel = [1.2 * w * (w * t).cos(), 1.2 * w * (2.0 * w * t).cos()];
+        let speed = vel[0].hypot(vel[1]);
+        // the field view points the robot along +y at a heading of zero
+        let heading = (-vel[0]).atan2(vel[1]);
+        // rate of change of the heading, used to split the speed between the sides
+        let accel = [
+            -1.2 * w * w * (w * t).sin(),
+            -2.4 * w * w * (2.0 * w * t).sin(),
+        ];
+        let turn_rate = (vel[0] * accel[1] - vel[1] * accel[0]) / (speed * speed).max(1e-6);
+
+        self.send(at, Event::Odom("robot".to_owned(), pos, heading));
+        let noisy_pos = [pos[0] + self.rng.noise(0.01), pos[1] + self.rng.noise(0.01)];
+        self.send(
+            at,
+            Event::Vec2("odom".to_owned(), "position".to_owned(), noisy_pos),
+        );
+        for (side, sign) in [("left", -1.0), ("right", 1.0)] {
+            let v = speed + sign * turn_rate * ROBOT_SIZE[0] / 2.0 + self.rng.noise(0.02);
+            // roughly ks + kv * v so the characterization tool has something to fit
+            let volts = 0.6 * v.signum() + 4.0 * v + self.rng.noise(0.1);
+            self.send(
+                at,
+                Event::Scalar("drive".to_owned(), format!("{side} velocity"), v),
+            );
+            self.send(
+                at,
+                Event::Scalar("drive".to_owned(), format!("{side} voltage"), volts),
+            );
+        }
+        let imu = [
+            accel[0] + self.rng.noise(0.3),
+            accel[1] + self.rng.noise(0.3),
+            9.81 + self.rng.noise(0.05),
+        ];
+        self.send(at, Event::Vec3("imu".to_owned(), "accel".to_owned(), imu));
+
+        // about one log every two seconds
+        if self.rng.unit() < dt / 2.0 {
+            let level = match self.rng.next_u64() % 10 {
+                0 => log::Level::Error,
+                1 | 2 => log::Level::Warn,
+                3 => log::Level::Debug,
+                _ => log::Level::Info,
+            };
+            let msg = MESSAGES[self.rng.next_u64() as usize % MESSAGES.len()].to_owned();
+            self.log(level, msg);
+        }
+    }
+}
+
+// lists random telemetry and the scripts in `dir()`, returns the mock picked
+pub fn draw_menu(ui: &mut Ui) -> Option<Mock> {
+    if ui.button("Random telemetry").clicked() {
+        ui.close_menu();
+        return Some(Mock::random(1));
+    }
+    ui.separator();
+    // no directory yet just means no scripts
+    let scripts = files::list(&dir(), EXTENSION).unwrap_or_default();
+    if scripts.is_empty() {
+        ui.weak(format!("No scripts in {}", dir().display()));
+    }
+    for (path, _) in scripts {
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        if ui.button(name).clicked() {
+            ui.close_menu();
+            match Mock::open(&path) {
+                Ok(mock) => return Some(mock),
+                Err(e) => log::warn!("Failed to open {}: {e}", path.display()),
+            }
+        }
+    }
+    None
+}
+
+fn parse_script(text: &str) -> Result<Vec<(Duration, Event)>, String> {
+    let mut events = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = |msg: &str| format!("line {}: {msg}", i + 1);
+        let mut parts = line.split_whitespace();
+        let mut next = || parts.next().ok_or_else(|| err("missing field"));
+        let at: f64 = next()?.parse().map_err(|_| err("bad time"))?;
+        let kind = next()?;
+        let event = match kind {
+            "log" => {
+                let level = next()?.parse().map_err(|_| err("bad level"))?;
+                let msg = line.splitn(4, char::is_whitespace).nth(3).unwrap_or("");
+                Event::Log(level, msg.trim().to_owned())
+            }
+            "scalar" | "vec2" | "vec3" => {
+                let (plot, subplot) = (next()?.to_owned(), next()?.to_owned());
+                let values = line
+                    .split_whitespace()
+                    .skip(4)
+                    .map(str::parse)
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|_| err("bad value"))?;
+                match (kind, values.as_slice()) {
+                    ("scalar", [v]) => Event::Scalar(plot, subplot, *v),
+                    ("vec2", [x, y]) => Event::Vec2(plot, subplot, [*x, *y]),
+                    ("vec3", [x, y, z]) => Event::Vec3(plot, subplot, [*x, *y, *z]),
+                    _ => return Err(err("wrong number of values")),
+                }
+            }
+            "odom" => {
+                let name = next()?.to_owned();
+                let values = line
+                    .split_whitespace()
+                    .skip(3)
+                    .map(str::parse)
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|_| err("bad value"))?;
+                let [x, y, heading] = values[..] else {
+                    return Err(err("odom needs x, y and heading"));
+                };
+                Event::Odom(name, [x, y], heading)
+            }
+            _ => return Err(err("unknown event")),
+        };
+        events.push((Duration::from_secs_f64(at.max(0.0)), event));
+    }
+    events.sort_by_key(|(at, _)| *at);
+    Ok(events)
+}
@@ -1,65 +1,624 @@
-use std::error::Error;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    time::{Duration, Instant, SystemTime},
+};
 
+use communication::packets::ToRobot;
 use eframe::egui;
-use egui::{containers::Window, Context};
+use egui::{Color32, Ui};
+use serde::{Deserialize, Serialize};
 
-pub struct Pid {
-    pub window: bool,
-    kp: String,
-    ki: String,
-    kd: String,
+use crate::{
+    autotune::{Action, Autotune},
+    logging::Logging,
+    simulation::Simulation,
+};
+
+// the protocol has no packet listing the robot's controllers, so they are
+// inferred from this graph: the robot reports the gains it is using as one
+// scalar subplot per term named "<controller>/<term>" (or just "<term>" for the
+// unnamed controller). this is a heuristic, a controller only shows up once it
+// has reported its gains and any other graph named "pid" is read as gains too
+// this is also how submitted gains are acknowledged
+const GAINS_PLOT: &str = "pid";
+const BASE_TERMS: [&str; 3] = ["kp", "ki", "kd"];
+// extra terms a controller can declare, with the label shown for them
+const EXTRA_TERMS: [(&str, &str); 3] = [
+    ("i_limit", "integral limit"),
+    ("out_clamp", "output clamp"),
+    ("kf", "feed-forward"),
+];
+// how long to wait for the robot to report the submitted gains
+const ACK_TIMEOUT: Duration = Duration::from_secs(2);
+const STORAGE_KEY: &str = "pid";
+// number of submitted gain sets remembered per controller
+const HISTORY_LEN: usize = 100;
+// minimum time between gains streamed to the robot while dragging a slider
+const STREAM_INTERVAL: Duration = Duration::from_millis(100);
+
+type GainSet = Vec<(String, f64)>;
+
+// presets and history are kept per controller name
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Library {
+    presets: BTreeMap<String, BTreeMap<String, GainSet>>,
+    // oldest first
+    history: BTreeMap<String, Vec<(SystemTime, GainSet)>>,
+    // controller -> term -> slider range
+    ranges: BTreeMap<String, BTreeMap<String, SliderRange>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SliderRange {
+    min: f64,
+    max: f64,
+    log: bool,
+}
+
+impl Default for SliderRange {
+    fn default() -> Self {
+        Self {
+            min: 0.0,
+            max: 1.0,
+            log: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Pending,
+    Acknowledged,
+    // the robot reported different gains after the update was sent
+    Rejected,
+    NoResponse,
+}
+
+struct Submission {
+    gains: GainSet,
+    at: Instant,
+    status: Status,
+}
+
+struct Term {
+    name: String,
+    input: String,
+    // last value the robot reported and when it arrived
+    live: Option<(f64, Instant)>,
+}
+
+struct Controller {
+    terms: Vec<Term>,
     err: Option<Box<dyn Error>>,
+    submitted: Option<Submission>,
+    // selected preset and the name to save the current gains under
+    preset: String,
+    new_preset: String,
+    sliders: bool,
+    // only send slider changes once the slider is released
+    hold: bool,
+    // a slider has changed since gains were last streamed
+    unsent: bool,
+    last_sent: Option<Instant>,
 }
 
-impl Default for Pid {
+impl Default for Controller {
     fn default() -> Self {
         Self {
-            window: false,
-            kp: String::new(),
-            ki: String::new(),
-            kd: String::new(),
+            terms: BASE_TERMS
+                .iter()
+                .map(|name| Term {
+                    name: (*name).to_owned(),
+                    input: String::new(),
+                    live: None,
+                })
+                .collect(),
             err: None,
+            submitted: None,
+            preset: String::new(),
+            new_preset: String::new(),
+            sliders: false,
+            hold: false,
+            unsent: false,
+            last_sent: None,
         }
     }
 }
 
+#[derive(Default)]
+pub struct Pid {
+    controllers: BTreeMap<String, Controller>,
+    selected: String,
+    library: Library,
+    view: View,
+    simulation: Simulation,
+    autotune: Autotune,
+}
+
+#[derive(Default, PartialEq)]
+enum View {
+    #[default]
+    Tune,
+    Simulate,
+    Autotune,
+}
+
 impl Pid {
-    pub fn draw(&mut self, ctx: &Context) -> Option<(f64, f64, f64)> {
-        let mut ret = None;
-        let mut open = self.window;
-        Window::new("UI Help")
-            .resizable(true)
-            .open(&mut open)
-            .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("kp");
-                    ui.text_edit_singleline(&mut self.kp);
-                });
-                ui.horizontal(|ui| {
-                    ui.label("ki");
-                    ui.text_edit_singleline(&mut self.ki);
-                });
-                ui.horizontal(|ui| {
-                    ui.label("kd");
-                    ui.text_edit_singleline(&mut self.kd);
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        Self {
+            library: storage
+                .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, &self.library);
+    }
+    pub fn add_buffer(&mut self, plot: &str, subplot: &str, buffer: &communication::Buffer) {
+        self.autotune.add_buffer(plot, subplot, buffer);
+        if plot != GAINS_PLOT {
+            return;
+        }
+        let (controller, term) = subplot.rsplit_once('/').unwrap_or(("", subplot));
+        let communication::Buffer::Scalar(v) = buffer else {
+            return;
+        };
+        let Some((_, value)) = v.last() else {
+            return;
+        };
+
+        let controller = self.controllers.entry(controller.to_owned()).or_default();
+        let idx = match controller.terms.iter().position(|t| t.name == term) {
+            Some(idx) => idx,
+            None => {
+                controller.terms.push(Term {
+                    name: term.to_owned(),
+                    input: String::new(),
+                    live: None,
                 });
-                if ui.button("Submit").clicked() {
-                    match self.parse() {
+                controller.terms.sort_by_key(|t| Self::term_order(&t.name));
+                controller
+                    .terms
+                    .iter()
+                    .position(|t| t.name == term)
+                    .unwrap()
+            }
+        };
+        controller.terms[idx].live = Some((*value, Instant::now()));
+    }
+    // base terms first, then declared extras, then anything else alphabetically
+    fn term_order(name: &str) -> (usize, String) {
+        let known = BASE_TERMS
+            .iter()
+            .chain(EXTRA_TERMS.iter().map(|(t, _)| t))
+            .position(|t| *t == name);
+        (known.unwrap_or(usize::MAX), name.to_owned())
+    }
+    fn term_label(name: &str) -> &str {
+        EXTRA_TERMS
+            .iter()
+            .find(|(t, _)| *t == name)
+            .map_or(name, |(_, label)| *label)
+    }
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<ToRobot> {
+        if self.controllers.is_empty() {
+            // nothing advertised yet, allow tuning the unnamed controller
+            self.controllers
+                .insert(String::new(), Controller::default());
+        }
+        if !self.controllers.contains_key(&self.selected) {
+            self.selected = self.controllers.keys().next().unwrap().clone();
+        }
+        if self.controllers.len() > 1 {
+            ui.horizontal_wrapped(|ui| {
+                for name in self.controllers.keys() {
+                    let label = if name.is_empty() {
+                        "default"
+                    } else {
+                        name.as_str()
+                    };
+                    ui.selectable_value(&mut self.selected, name.clone(), label);
+                }
+            });
+            ui.separator();
+        }
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.view, View::Tune, "Tune");
+            ui.selectable_value(&mut self.view, View::Simulate, "Simulate");
+            ui.selectable_value(&mut self.view, View::Autotune, "Autotune");
+        });
+        let name = self.selected.clone();
+        let controller = self.controllers.get_mut(&name).unwrap();
+        let terms = match self.view {
+            View::Tune => controller.ui(ui, &name, &mut self.library)?,
+            View::Simulate => {
+                self.simulation.ui(ui, controller.parse().ok());
+                return None;
+            }
+            View::Autotune => match self.autotune.ui(ui, &name)? {
+                // suggestions only cover kp, ki and kd so other terms keep their inputs
+                Action::Apply(gains) => {
+                    controller.set_inputs(&gains);
+                    self.view = View::Tune;
+                    if !controller.sendable(&name) {
+                        return None;
+                    }
+                    match controller.parse() {
                         Ok(v) => {
-                            ret = Some(v);
-                            self.err = None
+                            let history = self.library.history.entry(name.clone()).or_default();
+                            controller.submit(v, history)
+                        }
+                        Err(e) => {
+                            controller.err = Some(e);
+                            return None;
                         }
-                        Err(e) => self.err = Some(e),
                     }
                 }
-                if let Some(ref e) = self.err {
-                    ui.label(format!("{e}"));
+                Action::Save(preset, gains) => {
+                    controller.set_inputs(&gains);
+                    match controller.parse() {
+                        Ok(v) => {
+                            let presets = self.library.presets.entry(name).or_default();
+                            presets.insert(preset, v);
+                        }
+                        Err(e) => {
+                            controller.err = Some(e);
+                            self.view = View::Tune;
+                        }
+                    }
+                    return None;
+                }
+            },
+        };
+        // only sendable controllers submit gains, so these are kp, ki and kd
+        let [kp, ki, kd] = [0, 1, 2].map(|i| terms[i].1);
+        Some(ToRobot::Pid((kp, ki, kd)))
+    }
+}
+
+impl Controller {
+    // the protocol's only gains packet is kp, ki and kd for the robot's single
+    // controller, so other controllers can be watched but not tuned
+    fn sendable(&self, name: &str) -> bool {
+        name.is_empty() && self.terms.iter().map(|t| t.name.as_str()).eq(BASE_TERMS)
+    }
+    fn live_gains(&self) -> Option<Vec<f64>> {
+        self.terms.iter().map(|t| t.live.map(|(v, _)| v)).collect()
+    }
+    // check whether the robot has responded to the last submission
+    fn poll(&mut self) {
+        let Some(submitted) = &mut self.submitted else {
+            return;
+        };
+        if submitted.status != Status::Pending {
+            return;
+        }
+        let live: Vec<Option<(f64, Instant)>> = submitted
+            .gains
+            .iter()
+            .map(|(name, _)| {
+                self.terms
+                    .iter()
+                    .find(|t| t.name == *name)
+                    .and_then(|t| t.live)
+                    .filter(|(_, at)| *at > submitted.at)
+            })
+            .collect();
+        let reported = live.iter().all(Option::is_some);
+        let matches = reported
+            && live.iter().zip(&submitted.gains).all(|(live, (_, sent))| {
+                let live = live.unwrap().0;
+                (live - sent).abs() <= 1e-9 * sent.abs().max(1.0)
+            });
+        // the robot may report its old gains a few times before applying the
+        // new ones so only treat a mismatch as a rejection after the timeout
+        if matches {
+            submitted.status = Status::Acknowledged;
+        } else if submitted.at.elapsed() > ACK_TIMEOUT {
+            submitted.status = if reported {
+                Status::Rejected
+            } else {
+                Status::NoResponse
+            };
+        }
+    }
+    fn set_inputs(&mut self, gains: &GainSet) {
+        for (name, value) in gains {
+            if let Some(term) = self.terms.iter_mut().find(|t| t.name == *name) {
+                term.input = value.to_string();
+            }
+        }
+    }
+    fn submit(&mut self, gains: GainSet, history: &mut Vec<(SystemTime, GainSet)>) -> GainSet {
+        history.push((SystemTime::now(), gains.clone()));
+        if history.len() > HISTORY_LEN {
+            history.remove(0);
+        }
+        self.send(gains)
+    }
+    // send without recording in the history, used while streaming slider changes
+    fn send(&mut self, gains: GainSet) -> GainSet {
+        self.unsent = false;
+        self.last_sent = Some(Instant::now());
+        self.err = None;
+        self.submitted = Some(Submission {
+            gains: gains.clone(),
+            at: Instant::now(),
+            status: Status::Pending,
+        });
+        gains
+    }
+    fn ui(&mut self, ui: &mut Ui, name: &str, library: &mut Library) -> Option<GainSet> {
+        self.poll();
+        let mut ret = None;
+        let history = library.history.entry(name.to_owned()).or_default();
+        let ranges = library.ranges.entry(name.to_owned()).or_default();
+        let sendable = self.sendable(name);
+        if !sendable {
+            ui.colored_label(
+                Color32::YELLOW,
+                "Only kp, ki and kd of the robot's unnamed controller can be sent, \
+                 these gains are shown as the robot reports them",
+            );
+            self.sliders = false;
+        }
+        ui.horizontal(|ui| {
+            ui.add_enabled(sendable, egui::Checkbox::new(&mut self.sliders, "Sliders"));
+            if self.sliders {
+                ui.checkbox(&mut self.hold, "Hold (send on release)");
+            }
+        });
+
+        let (mut changed, mut released) = (false, false);
+        egui::Grid::new(("pid gains", name))
+            .num_columns(3)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("new");
+                ui.label("on robot");
+                ui.end_row();
+                for term in &mut self.terms {
+                    ui.label(Pid::term_label(&term.name));
+                    if self.sliders {
+                        let range = ranges.entry(term.name.clone()).or_default();
+                        let mut value = term.input.parse().unwrap_or(range.min);
+                        let resp = ui.add(
+                            egui::Slider::new(&mut value, range.min..=range.max)
+                                .logarithmic(range.log),
+                        );
+                        if resp.changed() {
+                            term.input = value.to_string();
+                            changed = true;
+                        }
+                        // clicks and keyboard changes count as a release
+                        released |= resp.drag_released() || (resp.changed() && !resp.dragged());
+                    } else {
+                        ui.text_edit_singleline(&mut term.input);
+                    }
+                    match term.live {
+                        Some((value, _)) => ui.label(format!("{value}")),
+                        None => ui.weak("unknown"),
+                    };
+                    ui.end_row();
+                }
+            });
+        if released {
+            // the final value is always sent and recorded, even when holding
+            if let Ok(v) = self.parse() {
+                ret = Some(self.submit(v, history));
+            }
+        } else if changed && !self.hold {
+            self.unsent = true;
+        }
+        if self.unsent {
+            if self
+                .last_sent
+                .map_or(true, |t| t.elapsed() >= STREAM_INTERVAL)
+            {
+                if let Ok(v) = self.parse() {
+                    ret = Some(self.send(v));
+                }
+            } else {
+                ui.ctx().request_repaint_after(STREAM_INTERVAL);
+            }
+        }
+        if self.sliders {
+            egui::CollapsingHeader::new("Slider ranges")
+                .id_source(("pid ranges", name))
+                .show(ui, |ui| Self::draw_ranges(ui, &self.terms, ranges));
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(sendable, egui::Button::new("Submit"))
+                .clicked()
+            {
+                match self.parse() {
+                    Ok(v) => ret = Some(self.submit(v, history)),
+                    Err(e) => self.err = Some(e),
+                }
+            }
+            if let Some(live) = self.live_gains() {
+                if ui.button("Copy from robot").clicked() {
+                    for (term, value) in self.terms.iter_mut().zip(live) {
+                        term.input = value.to_string();
+                    }
+                }
+            }
+        });
+        if let Some(ref e) = self.err {
+            ui.label(format!("{e}"));
+        }
+        if let Some(submitted) = &self.submitted {
+            let gains = submitted
+                .gains
+                .iter()
+                .map(|(_, v)| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let gains = format!("({gains})");
+            match submitted.status {
+                Status::Pending => {
+                    ui.ctx().request_repaint_after(ACK_TIMEOUT);
+                    ui.label(format!("Sent {gains}, waiting for the robot"))
+                }
+                Status::Acknowledged => {
+                    ui.colored_label(Color32::GREEN, format!("Robot is using {gains}"))
+                }
+                Status::Rejected => ui.colored_label(
+                    Color32::RED,
+                    format!("Robot rejected {gains} and kept its previous gains"),
+                ),
+                Status::NoResponse => ui.colored_label(
+                    Color32::RED,
+                    format!("Robot did not confirm {gains}, it may not have received them"),
+                ),
+            };
+        }
+
+        let presets = library.presets.entry(name.to_owned()).or_default();
+        egui::CollapsingHeader::new("Presets")
+            .id_source(("pid presets", name))
+            .show(ui, |ui| self.draw_presets(ui, presets));
+        egui::CollapsingHeader::new("History")
+            .id_source(("pid history", name))
+            .show(ui, |ui| {
+                if let Some(gains) = self.draw_history(ui, history, sendable) {
+                    ret = Some(gains);
                 }
             });
-        self.window = open;
         ret
     }
-    fn parse(&self) -> Result<(f64, f64, f64), Box<dyn Error>> {
-        Ok((self.kp.parse()?, self.ki.parse()?, self.kd.parse()?))
+    fn draw_ranges(ui: &mut Ui, terms: &[Term], ranges: &mut BTreeMap<String, SliderRange>) {
+        egui::Grid::new("pid ranges").num_columns(4).show(ui, |ui| {
+            ui.label("");
+            ui.label("min");
+            ui.label("max");
+            ui.label("log");
+            ui.end_row();
+            for term in terms {
+                let range = ranges.entry(term.name.clone()).or_default();
+                ui.label(Pid::term_label(&term.name));
+                ui.add(egui::DragValue::new(&mut range.min).speed(0.01));
+                ui.add(egui::DragValue::new(&mut range.max).speed(0.01));
+                ui.checkbox(&mut range.log, "");
+                range.max = range.max.max(range.min);
+                ui.end_row();
+            }
+        });
+    }
+    fn draw_presets(&mut self, ui: &mut Ui, presets: &mut BTreeMap<String, GainSet>) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("pid preset")
+                .selected_text(self.preset.as_str())
+                .show_ui(ui, |ui| {
+                    for preset in presets.keys() {
+                        ui.selectable_value(&mut self.preset, preset.clone(), preset.as_str());
+                    }
+                });
+            if let Some(gains) = presets.get(&self.preset) {
+                if ui.button("Load").clicked() {
+                    self.set_inputs(&gains.clone());
+                }
+                if ui.button("Delete").clicked() {
+                    presets.remove(&self.preset);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_preset);
+            if ui.button("Save current").clicked() && !self.new_preset.is_empty() {
+                match self.parse() {
+                    Ok(gains) => {
+                        self.preset = std::mem::take(&mut self.new_preset);
+                        presets.insert(self.preset.clone(), gains);
+                    }
+                    Err(e) => self.err = Some(e),
+                }
+            }
+        });
+
+        // compare the selected preset with what the robot is running
+        let Some(preset) = presets.get(&self.preset) else {
+            return;
+        };
+        egui::Grid::new("pid preset diff")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("preset");
+                ui.label("on robot");
+                ui.end_row();
+                for term in &self.terms {
+                    let preset = preset
+                        .iter()
+                        .find(|(t, _)| *t == term.name)
+                        .map(|(_, v)| *v);
+                    let live = term.live.map(|(v, _)| v);
+                    let fmt = |v: Option<f64>| v.map_or_else(|| "-".to_owned(), |v| v.to_string());
+                    ui.label(Pid::term_label(&term.name));
+                    ui.label(fmt(preset));
+                    if live == preset {
+                        ui.label(fmt(live));
+                    } else {
+                        ui.colored_label(Color32::RED, fmt(live));
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+    // returns the gains to resend if a previous set was reverted to
+    fn draw_history(
+        &mut self,
+        ui: &mut Ui,
+        history: &mut Vec<(SystemTime, GainSet)>,
+        sendable: bool,
+    ) -> Option<GainSet> {
+        if history.is_empty() {
+            ui.weak("Nothing submitted yet");
+            return None;
+        }
+        let mut revert = None;
+        egui::Grid::new("pid history")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, (at, gains)) in history.iter().enumerate().rev() {
+                    ui.label(Logging::format_timestamp(
+                        *at,
+                        time::macros::format_description!(
+                            "[year]-[month]-[day] [hour]:[minute]:[second]"
+                        ),
+                    ));
+                    ui.label(
+                        gains
+                            .iter()
+                            .map(|(t, v)| format!("{t}: {v}"))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    );
+                    if ui
+                        .add_enabled(sendable, egui::Button::new("Revert"))
+                        .clicked()
+                    {
+                        revert = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        let gains = history[revert?].1.clone();
+        self.set_inputs(&gains);
+        Some(self.submit(gains, history))
+    }
+    fn parse(&self) -> Result<GainSet, Box<dyn Error>> {
+        self.terms
+            .iter()
+            .map(|t| Ok((t.name.clone(), t.input.parse()?)))
+            .collect()
     }
 }
@@ -1,14 +1,18 @@
 use std::collections::HashMap;
 
 use crate::{
+    drift::Spread,
+    drive_sim::Drivetrain,
     robot::Robot,
+    routine::{self, Action},
     //robot_state::{ActionBuilderWindow, RobotState},
+    timeline::Timeline,
     tools::Tools,
     vec::Vec2,
 };
 //use communication::path::Action;
 use eframe::egui::{self, Context, Rgba, TextureHandle, TextureOptions};
-use egui_plot::{Line, PlotPoints, PlotUi, Points, Polygon};
+use egui_plot::{Line, LineStyle, PlotPoints, PlotUi, Points, Polygon};
 
 pub struct Plot {
     img: TextureHandle,
@@ -16,6 +20,11 @@ pub struct Plot {
     //pub action_builder_window: ActionBuilderWindow,
     tools: Tools,
     robots: HashMap<String, ([f64; 2], [f64; 2], f64)>,
+    // planned positions of the routine
+    path: Vec<Vec2>,
+    timeline: Timeline,
+    // where the robot may actually be after each action
+    drift: Vec<Spread>,
 }
 
 impl Plot {
@@ -33,6 +42,9 @@ impl Plot {
             action_builder_window: ActionBuilderWindow::new(),*/
             tools: Tools::default(),
             robots: HashMap::new(),
+            path: Vec::new(),
+            timeline: Timeline::default(),
+            drift: Vec::new(),
         }
     }
     fn load_field_image(ctx: &Context) -> TextureHandle {
@@ -49,7 +61,9 @@ impl Plot {
 
         ctx.load_texture("field", img, TextureOptions::default())
     }
-    pub fn draw(&mut self, ctx: &Context) {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::TopBottomPanel::bottom("timeline").show_inside(ui, |ui| self.timeline.ui(ui));
+
         let plot = egui_plot::Plot::new("plot")
             .view_aspect(1.0)
             .auto_bounds_x()
@@ -61,49 +75,97 @@ impl Plot {
             // 12 ft (width/length of field) to m
             [3.6576; 2],
         );
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let plot_resp = plot.show(ui, |plot_ui| {
-                plot_ui.image(img);
-
-                self.tools.draw(plot_ui);
-
-                for (robot_name, ([width, height], pos, heading)) in self.robots.iter() {
-                    // draw name at robot pos
-                    plot_ui.text(
-                        egui_plot::Text::new((*pos).into(), robot_name).color(egui::Color32::GOLD),
-                    );
-                    let hwidth = 0.5 * width;
-                    let hheight = 0.5 * height;
-                    let mut points = [
-                        [-hwidth, -hheight],
-                        [-hwidth, hheight],
-                        [hwidth, hheight],
-                        [hwidth, -hheight],
-                        [-0.5 * hwidth, 1.1 * hheight],
-                        [0.0, 1.2 * hheight],
-                        [0.5 * hwidth, 1.1 * hheight],
-                    ];
-
-                    let (s, c) = heading.sin_cos();
-
-                    for point in points.iter_mut() {
-                        // rotate points
-                        *point = [
-                            point[0] * c - point[1] * s + pos[0],
-                            point[0] * s + point[1] * c + pos[1],
-                        ];
-                    }
-
-                    let rect = Polygon::new(points[..4].to_vec()).color(egui::Color32::GREEN);
-                    let arrow = Line::new(points[4..].to_vec()).color(egui::Color32::GREEN);
-
-                    plot_ui.polygon(rect);
-                    plot_ui.line(arrow)
-                }
-            });
-
-            self.tools.draw_defered(ui, &plot_resp);
+        let plot_resp = plot.show(ui, |plot_ui| {
+            plot_ui.image(img);
+
+            self.tools.draw(plot_ui);
+
+            let path_color = Rgba::from(egui::Color32::LIGHT_BLUE);
+            Self::draw_lines(plot_ui, &self.path, path_color);
+            Self::draw_points(plot_ui, &self.path, path_color);
+
+            Self::draw_drift(plot_ui, &self.drift, egui::Color32::LIGHT_RED);
+
+            if let Some(sample) = self.timeline.current() {
+                let pose = sample.pose;
+                let size = self.timeline.size();
+                Self::draw_robot(plot_ui, size, pose.pos.0, pose.heading, path_color.into());
+            }
+
+            for (robot_name, (dim, pos, heading)) in self.robots.iter() {
+                // draw name at robot pos
+                plot_ui.text(
+                    egui_plot::Text::new((*pos).into(), robot_name).color(egui::Color32::GOLD),
+                );
+                Self::draw_robot(plot_ui, *dim, *pos, *heading, egui::Color32::GREEN);
+            }
         });
+
+        self.tools.draw_defered(ui, &plot_resp);
+    }
+    // rectangle with an arrow on the front
+    pub fn draw_robot(
+        ui: &mut PlotUi,
+        [width, height]: [f64; 2],
+        pos: [f64; 2],
+        heading: f64,
+        color: egui::Color32,
+    ) {
+        let hwidth = 0.5 * width;
+        let hheight = 0.5 * height;
+        let mut points = [
+            [-hwidth, -hheight],
+            [-hwidth, hheight],
+            [hwidth, hheight],
+            [hwidth, -hheight],
+            [-0.5 * hwidth, 1.1 * hheight],
+            [0.0, 1.2 * hheight],
+            [0.5 * hwidth, 1.1 * hheight],
+        ];
+
+        let (s, c) = heading.sin_cos();
+
+        for point in points.iter_mut() {
+            // rotate points
+            *point = [
+                point[0] * c - point[1] * s + pos[0],
+                point[0] * s + point[1] * c + pos[1],
+            ];
+        }
+
+        let rect = Polygon::new(points[..4].to_vec()).color(color);
+        let arrow = Line::new(points[4..].to_vec()).color(color);
+
+        ui.polygon(rect);
+        ui.line(arrow)
+    }
+    // 1σ and 2σ ellipses after each action and where the runs ended
+    pub fn draw_drift(ui: &mut PlotUi, spreads: &[Spread], color: egui::Color32) {
+        for spread in spreads {
+            ui.line(Line::new(spread.ellipse(1.0)).color(color));
+            ui.line(
+                Line::new(spread.ellipse(2.0))
+                    .color(color)
+                    .style(LineStyle::dashed_loose()),
+            );
+        }
+        if let Some(end) = spreads.last() {
+            let positions = end.positions.iter().map(|pos| pos.0).collect();
+            ui.points(
+                Points::new(PlotPoints::new(positions))
+                    .color(color)
+                    .radius(1.5),
+            );
+        }
+    }
+    pub fn set_drift(&mut self, spreads: Vec<Spread>) {
+        self.drift = spreads;
+    }
+    // the routine shown on the field and followed by the timeline
+    pub fn set_routine(&mut self, actions: &[Action], drivetrain: Drivetrain) {
+        if self.timeline.set_routine(actions, drivetrain) {
+            self.path = routine::plan(actions).iter().map(|pose| pose.pos).collect();
+        }
     }
     pub fn set_tools(&mut self, tools: Tools) {
         self.tools = tools;
@@ -122,4 +184,14 @@ impl Plot {
     pub fn set_odom(&mut self, name: String, dim: [f64; 2], pos: [f64; 2], heading: f64) {
         let _ = self.robots.insert(name, (dim, pos, heading));
     }
+    // dimensions, position and heading of a robot
+    pub fn odom(&self, name: &str) -> Option<([f64; 2], [f64; 2], f64)> {
+        self.robots.get(name).copied()
+    }
+    pub fn remove_odom(&mut self, name: &str) {
+        self.robots.remove(name);
+    }
+    pub fn clear_odom(&mut self) {
+        self.robots.clear();
+    }
 }
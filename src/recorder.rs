@@ -0,0 +1,92 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use communication::packets::ToClient;
+use eframe::egui;
+use egui::{Color32, Ui};
+
+use crate::session_file::{self, Metadata, SessionWriter};
+
+// records every packet received from the robot while enabled
+#[derive(Default)]
+pub struct Recorder {
+    writer: Option<SessionWriter>,
+    // metadata for the next recording
+    robot: String,
+    notes: String,
+}
+
+impl Recorder {
+    pub fn record(&mut self, pkts: &[ToClient]) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        let received = SystemTime::now();
+        for pkt in pkts {
+            if let Err(e) = writer.write(received, pkt) {
+                log::warn!("Failed to record packet, stopping the recording: {e}");
+                self.writer = None;
+                return;
+            }
+        }
+    }
+    fn start(&mut self, client: &str) {
+        let metadata = Metadata {
+            robot: self.robot.clone(),
+            client: client.to_owned(),
+            app_version: env!("CARGO_PKG_VERSION").to_owned(),
+            notes: self.notes.clone(),
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        match SessionWriter::create(&session_file::dir(), &metadata) {
+            Ok(writer) => {
+                log::info!("Recording to {}", writer.path().display());
+                self.writer = Some(writer);
+            }
+            Err(e) => log::warn!("Failed to start recording: {e}"),
+        }
+    }
+    pub fn draw_menu(&mut self, ui: &mut Ui, client: &str) {
+        let mut recording = self.writer.is_some();
+        if ui.checkbox(&mut recording, "Record session").changed() {
+            if recording {
+                self.start(client);
+            } else {
+                // dropping the writer finishes the file
+                self.writer = None;
+            }
+        }
+        if let Some(writer) = &self.writer {
+            ui.weak(format!("Writing {}", writer.path().display()));
+            return;
+        }
+
+        egui::Grid::new("recording metadata")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Robot");
+                ui.text_edit_singleline(&mut self.robot);
+                ui.end_row();
+                ui.label("Notes");
+                ui.text_edit_multiline(&mut self.notes);
+                ui.end_row();
+            });
+        ui.menu_button("Continue a recording", |ui| {
+            let Some(path) = session_file::draw_sessions_menu(ui) else {
+                return;
+            };
+            match SessionWriter::append(&path) {
+                Ok(writer) => self.writer = Some(writer),
+                Err(e) => log::warn!("Failed to open {}: {e}", path.display()),
+            }
+        });
+    }
+    pub fn draw_status(&self, ui: &mut Ui) {
+        if let Some(writer) = &self.writer {
+            ui.colored_label(Color32::RED, "⏺ REC")
+                .on_hover_text(writer.path().display().to_string());
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use communication::packets::ToClient;
+use eframe::egui;
+use egui::Ui;
+
+use crate::session_file::{self, Metadata};
+
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 8.0;
+
+// plays back a recorded session in place of the live connection
+pub struct Replay {
+    path: PathBuf,
+    metadata: Metadata,
+    reader: BufReader<File>,
+    // receive time in microseconds and file offset of each record
+    records: Vec<(u64, u64)>,
+    // next record to deliver
+    next: usize,
+    // playback position in microseconds since the start of the recording
+    clock: u64,
+    playing: bool,
+    speed: f64,
+    last_frame: Option<Instant>,
+    step: bool,
+    // everything shown has to be cleared before the next packets, set when
+    // seeking backwards
+    reset: bool,
+}
+
+impl Replay {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let index = session_file::read_index(path)?;
+        Ok(Self {
+            path: path.to_owned(),
+            metadata: index.metadata,
+            reader: BufReader::new(File::open(path)?),
+            records: index.records,
+            next: 0,
+            clock: 0,
+            playing: true,
+            speed: 1.0,
+            last_frame: None,
+            step: false,
+            reset: true,
+        })
+    }
+    fn duration(&self) -> u64 {
+        self.records.last().map_or(0, |(t, _)| *t)
+    }
+    pub fn take_reset(&mut self) -> bool {
+        std::mem::take(&mut self.reset)
+    }
+    fn seek(&mut self, micros: u64) {
+        if micros < self.clock {
+            self.reset = true;
+            self.next = 0;
+        }
+        self.clock = micros;
+    }
+    // packets up to the current playback position, in the same form as the
+    // live connection returns them
+    pub fn get_packets(&mut self) -> Vec<ToClient> {
+        let now = Instant::now();
+        if self.playing {
+            if let Some(last) = self.last_frame {
+                self.clock += ((now - last).as_secs_f64() * self.speed * 1e6) as u64;
+            }
+            self.last_frame = Some(now);
+        } else {
+            self.last_frame = None;
+        }
+
+        let end = if std::mem::take(&mut self.step) {
+            let end = (self.next + 1).min(self.records.len());
+            if let Some((t, _)) = self.records.get(self.next) {
+                self.clock = *t;
+            }
+            end
+        } else {
+            self.next + self.records[self.next..].partition_point(|(t, _)| *t <= self.clock)
+        };
+
+        let mut pkts = Vec::with_capacity(end - self.next);
+        for &(_, offset) in &self.records[self.next..end] {
+            match session_file::read_record(&mut self.reader, offset) {
+                Ok(pkt) => pkts.push(pkt),
+                Err(e) => log::warn!("Skipping unreadable packet in {}: {e}", self.path.display()),
+            }
+        }
+        self.next = end;
+        if self.next == self.records.len() {
+            self.playing = false;
+        }
+        pkts
+    }
+    // returns false once the replay should be closed
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        let mut keep = true;
+        ui.horizontal(|ui| {
+            let name = self
+                .path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let Metadata {
+                robot,
+                client,
+                app_version,
+                notes,
+                ..
+            } = &self.metadata;
+            ui.label(format!("Replaying {name}")).on_hover_text(format!(
+                "robot: {robot}\nclient: {client}\nrecorded with version {app_version}\n{notes}"
+            ));
+            if ui.button("Back to live").clicked() {
+                keep = false;
+            }
+            ui.separator();
+
+            let at_end = self.next == self.records.len();
+            let label = if self.playing { "⏸" } else { "▶" };
+            if ui.button(label).clicked() {
+                if at_end && !self.playing {
+                    self.seek(0);
+                }
+                self.playing = !self.playing;
+            }
+            if ui
+                .add_enabled(!at_end, egui::Button::new("⏭"))
+                .on_hover_text("Step one packet")
+                .clicked()
+            {
+                self.playing = false;
+                self.step = true;
+            }
+            ui.add(
+                egui::Slider::new(&mut self.speed, MIN_SPEED..=MAX_SPEED)
+                    .logarithmic(true)
+                    .suffix("×"),
+            );
+            ui.label(format!("packet {}/{}", self.next, self.records.len()));
+        });
+
+        let duration = self.duration();
+        ui.spacing_mut().slider_width = (ui.available_width() - 150.0).max(100.0);
+        let mut secs = self.clock.min(duration) as f64 / 1e6;
+        let resp = ui.add(
+            egui::Slider::new(&mut secs, 0.0..=duration as f64 / 1e6)
+                .show_value(false)
+                .text(format!(
+                    "{} / {}",
+                    format_duration(self.clock.min(duration)),
+                    format_duration(duration)
+                )),
+        );
+        if resp.changed() {
+            self.seek((secs * 1e6) as u64);
+        }
+        if self.playing {
+            ui.ctx().request_repaint();
+        }
+        keep
+    }
+}
+
+fn format_duration(micros: u64) -> String {
+    let secs = micros / 1_000_000;
+    format!(
+        "{}:{:02}.{}",
+        secs / 60,
+        secs % 60,
+        micros % 1_000_000 / 100_000
+    )
+}
@@ -0,0 +1,29 @@
+use std::f64::consts::TAU;
+
+// xorshift, good enough for noise without another dependency
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // a zero state would stay zero
+        Self(seed.max(1))
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    // uniform in (0, 1]
+    pub fn unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+    // uniform in [-amplitude, amplitude]
+    pub fn noise(&mut self, amplitude: f64) -> f64 {
+        (self.unit() * 2.0 - 1.0) * amplitude
+    }
+    // standard normal, box-muller
+    pub fn gaussian(&mut self) -> f64 {
+        (-2.0 * self.unit().ln()).sqrt() * (TAU * self.unit()).cos()
+    }
+}
@@ -0,0 +1,232 @@
+use std::f64::consts::PI;
+
+use eframe::egui;
+use egui::{DragValue, Ui};
+use serde::{Deserialize, Serialize};
+
+use crate::vec::Vec2;
+
+const STORAGE_KEY: &str = "routine";
+
+// steps of an autonomous routine. distances are in metres and angles in
+// radians, counter-clockwise with a heading of zero facing +y
+//
+// the Abs variants are relative to where the previous actions should have
+// left the robot rather than where it thinks it is, so they correct for drift
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    StartAt { pos: [f64; 2], heading: f64 },
+    MoveRel { rel: f64 },
+    MoveRelAbs { rel: f64 },
+    MoveTo { pos: [f64; 2] },
+    TurnRel { angle: f64 },
+    TurnRelAbs { angle: f64 },
+    TurnTo { heading: f64 },
+}
+
+impl Action {
+    const ALL: [Action; 7] = [
+        Action::StartAt {
+            pos: [0.0, 0.0],
+            heading: 0.0,
+        },
+        Action::MoveRel { rel: 0.5 },
+        Action::MoveRelAbs { rel: 0.5 },
+        Action::MoveTo { pos: [0.0, 0.0] },
+        Action::TurnRel { angle: PI / 2.0 },
+        Action::TurnRelAbs { angle: PI / 2.0 },
+        Action::TurnTo { heading: 0.0 },
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::StartAt { .. } => "StartAt",
+            Action::MoveRel { .. } => "MoveRel",
+            Action::MoveRelAbs { .. } => "MoveRelAbs",
+            Action::MoveTo { .. } => "MoveTo",
+            Action::TurnRel { .. } => "TurnRel",
+            Action::TurnRelAbs { .. } => "TurnRelAbs",
+            Action::TurnTo { .. } => "TurnTo",
+        }
+    }
+    // the pose the action should leave the robot in
+    pub fn apply(&self, pose: Pose) -> Pose {
+        match *self {
+            Action::StartAt { pos, heading } => Pose {
+                pos: pos.into(),
+                heading,
+            },
+            Action::MoveRel { rel } | Action::MoveRelAbs { rel } => Pose {
+                pos: pose.pos + pose.forward() * rel,
+                ..pose
+            },
+            Action::MoveTo { pos } => Pose {
+                pos: pos.into(),
+                heading: pose.heading_to(pos.into()).unwrap_or(pose.heading),
+            },
+            Action::TurnRel { angle } | Action::TurnRelAbs { angle } => Pose {
+                heading: pose.heading + angle,
+                ..pose
+            },
+            Action::TurnTo { heading } => Pose {
+                heading: pose.heading + wrap_angle(heading - pose.heading),
+                ..pose
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Pose {
+    pub pos: Vec2,
+    pub heading: f64,
+}
+
+impl Pose {
+    pub fn forward(&self) -> Vec2 {
+        let (s, c) = self.heading.sin_cos();
+        Vec2([-s, c])
+    }
+    // heading that faces `target`, None if already there
+    pub fn heading_to(&self, target: Vec2) -> Option<f64> {
+        let d = target - self.pos;
+        (d.mag() > 1e-9).then(|| (-d.x()).atan2(d.y()))
+    }
+}
+
+// the equivalent angle in [-π, π]
+pub fn wrap_angle(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+// planned pose after each action, starting from the origin
+pub fn plan(actions: &[Action]) -> Vec<Pose> {
+    actions
+        .iter()
+        .scan(Pose::default(), |pose, action| {
+            *pose = action.apply(*pose);
+            Some(*pose)
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Routine {
+    pub actions: Vec<Action>,
+}
+
+impl Default for Routine {
+    fn default() -> Self {
+        Self {
+            actions: vec![
+                Action::StartAt {
+                    pos: [0.0, -1.7],
+                    heading: 0.0,
+                },
+                Action::MoveRelAbs { rel: 0.2 },
+                Action::MoveRel { rel: 1.0 },
+                Action::TurnTo { heading: PI / 2.0 },
+                Action::MoveTo { pos: [-1.0, 0.0] },
+            ],
+        }
+    }
+}
+
+impl Routine {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+    // returns true if the actions were changed
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        let mut swap = None;
+        let mut remove = None;
+        let len = self.actions.len();
+        egui::Grid::new("routine")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, action) in self.actions.iter_mut().enumerate() {
+                    ui.label(i.to_string());
+                    egui::ComboBox::from_id_source(("routine action", i))
+                        .selected_text(action.name())
+                        .show_ui(ui, |ui| {
+                            for kind in Action::ALL {
+                                let same =
+                                    std::mem::discriminant(&kind) == std::mem::discriminant(action);
+                                if ui.selectable_label(same, kind.name()).clicked() && !same {
+                                    *action = kind;
+                                    changed = true;
+                                }
+                            }
+                        });
+                    ui.horizontal(|ui| changed |= Self::action_ui(ui, action));
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                            swap = Some(i - 1);
+                        }
+                        if ui
+                            .add_enabled(i + 1 < len, egui::Button::new("⏷"))
+                            .clicked()
+                        {
+                            swap = Some(i);
+                        }
+                        if ui.button("🗑").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = swap {
+            self.actions.swap(i, i + 1);
+            changed = true;
+        }
+        if let Some(i) = remove {
+            self.actions.remove(i);
+            changed = true;
+        }
+        ui.menu_button("Add action", |ui| {
+            for kind in Action::ALL {
+                if ui.button(kind.name()).clicked() {
+                    self.actions.push(kind);
+                    changed = true;
+                    ui.close_menu();
+                }
+            }
+        });
+        changed
+    }
+    fn action_ui(ui: &mut Ui, action: &mut Action) -> bool {
+        let distance = |ui: &mut Ui, value: &mut f64| {
+            ui.add(DragValue::new(value).speed(0.01).suffix(" m"))
+                .changed()
+        };
+        let angle = |ui: &mut Ui, value: &mut f64| {
+            let mut degrees = value.to_degrees();
+            let changed = ui
+                .add(DragValue::new(&mut degrees).speed(1.0).suffix("°"))
+                .changed();
+            if changed {
+                *value = degrees.to_radians();
+            }
+            changed
+        };
+        match action {
+            Action::StartAt { pos, heading } => {
+                distance(ui, &mut pos[0]) | distance(ui, &mut pos[1]) | angle(ui, heading)
+            }
+            Action::MoveRel { rel } | Action::MoveRelAbs { rel } => distance(ui, rel),
+            Action::MoveTo { pos } => distance(ui, &mut pos[0]) | distance(ui, &mut pos[1]),
+            Action::TurnRel { angle: value } | Action::TurnRelAbs { angle: value } => {
+                angle(ui, value)
+            }
+            Action::TurnTo { heading } => angle(ui, heading),
+        }
+    }
+}
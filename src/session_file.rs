@@ -0,0 +1,263 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use communication::packets::ToClient;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::files;
+
+const EXTENSION: &str = "session";
+const MAGIC: &[u8; 8] = b"PGESESS1";
+const INDEX_MAGIC: &[u8; 8] = b"PGEINDEX";
+
+// packet recordings, all integers are little endian
+//
+// header:  magic, u32 metadata length, messagepack metadata
+// records: u64 microseconds since the session started, u32 length, messagepack ToClient
+//          (the same encoding the robot sends)
+// footer:  (u64 time, u64 offset of the record) per record, u64 offset of the
+//          footer, index magic
+//
+// the footer is only written when the file is closed cleanly. appending cuts it
+// off and rewrites it on close, and files without one are indexed by scanning
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    pub robot: String,
+    pub client: String,
+    pub app_version: String,
+    pub notes: String,
+    // milliseconds since the unix epoch
+    pub started: u64,
+}
+
+impl Metadata {
+    pub fn start_time(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(self.started)
+    }
+}
+
+pub struct Index {
+    pub metadata: Metadata,
+    // receive time in microseconds and file offset of each record
+    pub records: Vec<(u64, u64)>,
+    // end of the last complete record
+    pub end: u64,
+}
+
+pub struct SessionWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    start: SystemTime,
+    offset: u64,
+    records: Vec<(u64, u64)>,
+}
+
+impl SessionWriter {
+    // starts a recording in `dir`, named after when it started. names only
+    // go down to the second, so a number is added rather than overwriting
+    // another recording from the same second
+    pub fn create(dir: &Path, metadata: &Metadata) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let start = metadata.start_time();
+        let name = time::OffsetDateTime::from(start)
+            .format(time::macros::format_description!(
+                "[year]-[month]-[day]_[hour]-[minute]-[second]"
+            ))
+            .unwrap();
+        let mut copy = 0;
+        let (path, file) = loop {
+            let path = match copy {
+                0 => dir.join(format!("{name}.{EXTENSION}")),
+                _ => dir.join(format!("{name}-{copy}.{EXTENSION}")),
+            };
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => copy += 1,
+                Err(e) => return Err(e),
+            }
+        };
+        let mut file = BufWriter::new(file);
+        let header = rmp_serde::to_vec(metadata).map_err(io::Error::other)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&(header.len() as u32).to_le_bytes())?;
+        file.write_all(&header)?;
+        Ok(Self {
+            path,
+            file,
+            start,
+            offset: (MAGIC.len() + 4 + header.len()) as u64,
+            records: Vec::new(),
+        })
+    }
+    // continue a previous recording, new packets keep counting from its start
+    pub fn append(path: &Path) -> io::Result<Self> {
+        let index = read_index(path)?;
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(index.end)?;
+        let mut file = BufWriter::new(file);
+        file.seek(SeekFrom::Start(index.end))?;
+        Ok(Self {
+            path: path.to_owned(),
+            file,
+            start: index.metadata.start_time(),
+            offset: index.end,
+            records: index.records,
+        })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn write(&mut self, received: SystemTime, pkt: &ToClient) -> io::Result<()> {
+        let micros = received
+            .duration_since(self.start)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let data = rmp_serde::to_vec(pkt).map_err(io::Error::other)?;
+        self.file.write_all(&micros.to_le_bytes())?;
+        self.file.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.file.write_all(&data)?;
+        self.records.push((micros, self.offset));
+        self.offset += 12 + data.len() as u64;
+        Ok(())
+    }
+    fn write_footer(&mut self) -> io::Result<()> {
+        for (micros, offset) in &self.records {
+            self.file.write_all(&micros.to_le_bytes())?;
+            self.file.write_all(&offset.to_le_bytes())?;
+        }
+        self.file.write_all(&self.offset.to_le_bytes())?;
+        self.file.write_all(INDEX_MAGIC)?;
+        self.file.flush()
+    }
+}
+
+impl Drop for SessionWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.write_footer() {
+            log::warn!("Failed to write the index of {}: {e}", self.path.display());
+        }
+    }
+}
+
+pub fn dir() -> PathBuf {
+    files::data_dir("recordings")
+}
+
+// all recordings with their size, oldest first
+pub fn sessions() -> io::Result<Vec<(PathBuf, u64)>> {
+    files::list(&dir(), EXTENSION)
+}
+
+// lists the recordings newest first and returns the one clicked
+pub fn draw_sessions_menu(ui: &mut egui::Ui) -> Option<PathBuf> {
+    let sessions = match sessions() {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            ui.label(format!("No recordings: {e}"));
+            return None;
+        }
+    };
+    for (path, _) in sessions.into_iter().rev() {
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        if ui.button(name).clicked() {
+            ui.close_menu();
+            return Some(path);
+        }
+    }
+    None
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_header(reader: &mut impl Read) -> io::Result<(Metadata, u64)> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a session recording",
+        ));
+    }
+    let len = read_u32(reader)?;
+    let mut header = vec![0; len as usize];
+    reader.read_exact(&mut header)?;
+    let metadata = rmp_serde::from_slice(&header).map_err(io::Error::other)?;
+    Ok((metadata, (MAGIC.len() + 4) as u64 + len as u64))
+}
+
+// the index from the footer, or rebuilt by scanning if the recording wasn't closed cleanly
+pub fn read_index(path: &Path) -> io::Result<Index> {
+    let mut file = BufReader::new(File::open(path)?);
+    let (metadata, data_start) = read_header(&mut file)?;
+    let len = file.get_ref().metadata()?.len();
+
+    if len >= data_start + 16 {
+        file.seek(SeekFrom::Start(len - 16))?;
+        let footer = read_u64(&mut file)?;
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        let valid = footer >= data_start && footer <= len - 16 && (len - 16 - footer) % 16 == 0;
+        if &magic == INDEX_MAGIC && valid {
+            file.seek(SeekFrom::Start(footer))?;
+            let records = (0..(len - 16 - footer) / 16)
+                .map(|_| Ok((read_u64(&mut file)?, read_u64(&mut file)?)))
+                .collect::<io::Result<_>>()?;
+            return Ok(Index {
+                metadata,
+                records,
+                end: footer,
+            });
+        }
+    }
+
+    log::warn!(
+        "{} has no index, it was probably not closed cleanly. Scanning it instead",
+        path.display()
+    );
+    file.seek(SeekFrom::Start(data_start))?;
+    let mut records = Vec::new();
+    let mut end = data_start;
+    while end + 12 <= len {
+        let micros = read_u64(&mut file)?;
+        let size = read_u32(&mut file)? as u64;
+        if end + 12 + size > len {
+            break;
+        }
+        file.seek_relative(size as i64)?;
+        records.push((micros, end));
+        end += 12 + size;
+    }
+    Ok(Index {
+        metadata,
+        records,
+        end,
+    })
+}
+
+// read the packet of the record starting at `offset`
+pub fn read_record(reader: &mut (impl Read + Seek), offset: u64) -> io::Result<ToClient> {
+    reader.seek(SeekFrom::Start(offset + 8))?;
+    let size = read_u32(reader)?;
+    let mut data = vec![0; size as usize];
+    reader.read_exact(&mut data)?;
+    rmp_serde::from_slice(&data).map_err(io::Error::other)
+}
@@ -0,0 +1,376 @@
+use eframe::egui;
+use egui::Ui;
+
+use crate::graph::Graph;
+
+// battery voltage the drivetrain output is clamped to
+const MAX_VOLTAGE: f64 = 12.0;
+// the plant is integrated with smaller steps than the controller runs at
+const SUBSTEPS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Plant {
+    // dy/dt = (gain * u - y) / tau
+    FirstOrder { gain: f64, tau: f64 },
+    // y'' = gain * wn^2 * u - 2 * zeta * wn * y' - wn^2 * y
+    SecondOrder { gain: f64, wn: f64, zeta: f64 },
+    // position of a drivetrain driven by dc motors, the output is the voltage
+    Drivetrain(Drivetrain),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Drivetrain {
+    // kg
+    mass: f64,
+    // m
+    wheel_radius: f64,
+    // motor rotations per wheel rotation
+    gear_ratio: f64,
+    motors: f64,
+    // N m / A
+    kt: f64,
+    // rad/s / V
+    kv: f64,
+    // ohms
+    resistance: f64,
+}
+
+impl Default for Drivetrain {
+    fn default() -> Self {
+        Self {
+            mass: 15.0,
+            wheel_radius: 0.041,
+            gear_ratio: 1.67,
+            motors: 6.0,
+            kt: 0.0147,
+            kv: 20.9,
+            resistance: 2.0,
+        }
+    }
+}
+
+impl Drivetrain {
+    fn acceleration(&self, voltage: f64, velocity: f64) -> f64 {
+        let motor_speed = velocity / self.wheel_radius * self.gear_ratio;
+        let current = (voltage - motor_speed / self.kv) / self.resistance;
+        let torque = self.motors * self.kt * current * self.gear_ratio;
+        torque / self.wheel_radius / self.mass
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Setpoint {
+    Step(f64),
+    // units per second
+    Ramp(f64),
+}
+
+impl Setpoint {
+    fn at(&self, t: f64) -> f64 {
+        match self {
+            Self::Step(value) => *value,
+            Self::Ramp(rate) => rate * t,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    plant: Plant,
+    setpoint: Setpoint,
+    duration: f64,
+    // controller period
+    dt: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            plant: Plant::FirstOrder {
+                gain: 1.0,
+                tau: 0.5,
+            },
+            setpoint: Setpoint::Step(1.0),
+            duration: 5.0,
+            dt: 0.01,
+        }
+    }
+}
+
+// gains by term name, the same as what is sent to the robot
+type Gains = [(String, f64)];
+
+pub struct Simulation {
+    config: Config,
+    // inputs the graphs were last simulated with
+    simulated: Option<(Config, Vec<(String, f64)>)>,
+    response: Graph,
+    effort: Graph,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            config: Config::default(),
+            simulated: None,
+            response: Graph::new("simulated response".to_owned()),
+            effort: Graph::new("simulated control effort".to_owned()),
+        }
+    }
+}
+
+impl Simulation {
+    fn run(&mut self, gains: &Gains) {
+        let gain = |name: &str| {
+            gains
+                .iter()
+                .find(|(t, _)| t == name)
+                .map_or(0.0, |(_, v)| *v)
+        };
+        let (kp, ki, kd, kf) = (gain("kp"), gain("ki"), gain("kd"), gain("kf"));
+        // limits that aren't set or are zero are treated as no limit
+        let limit = |name: &str| Some(gain(name)).filter(|v| *v > 0.0);
+        let (i_limit, out_clamp) = (limit("i_limit"), limit("out_clamp"));
+        let Config {
+            plant,
+            setpoint,
+            duration,
+            dt,
+        } = self.config;
+
+        self.response.clear();
+        self.effort.clear();
+        // plant output and its derivative
+        let (mut y, mut dy) = (0.0, 0.0);
+        let (mut integral, mut prev_err) = (0.0, None);
+        let steps = (duration / dt).ceil() as usize;
+        for i in 0..=steps {
+            let t = i as f64 * dt;
+            let target = setpoint.at(t);
+            let err = target - y;
+            integral += err * dt;
+            if let Some(limit) = i_limit {
+                integral = integral.clamp(-limit, limit);
+            }
+            let derivative = prev_err.map_or(0.0, |prev| (err - prev) / dt);
+            prev_err = Some(err);
+            let mut u = kp * err + ki * integral + kd * derivative + kf * target;
+            if let Some(clamp) = out_clamp {
+                u = u.clamp(-clamp, clamp);
+            }
+            if let Plant::Drivetrain(_) = plant {
+                u = u.clamp(-MAX_VOLTAGE, MAX_VOLTAGE);
+            }
+
+            self.response.add_point("setpoint", [t, target]);
+            self.response.add_point("output", [t, y]);
+            self.effort.add_point("effort", [t, u]);
+
+            let h = dt / SUBSTEPS as f64;
+            for _ in 0..SUBSTEPS {
+                let ddy = match plant {
+                    Plant::FirstOrder { gain, tau } => {
+                        dy = (gain * u - y) / tau;
+                        0.0
+                    }
+                    Plant::SecondOrder { gain, wn, zeta } => {
+                        gain * wn * wn * u - 2.0 * zeta * wn * dy - wn * wn * y
+                    }
+                    Plant::Drivetrain(drivetrain) => drivetrain.acceleration(u, dy),
+                };
+                dy += ddy * h;
+                y += dy * h;
+            }
+        }
+    }
+    pub fn ui(&mut self, ui: &mut Ui, gains: Option<Vec<(String, f64)>>) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.draw_config(ui);
+            ui.separator();
+            let Some(gains) = gains else {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    "Enter valid gains for this controller to simulate them",
+                );
+                return;
+            };
+            let inputs = (self.config.clone(), gains);
+            if self.simulated.as_ref() != Some(&inputs) {
+                self.run(&inputs.1);
+                self.simulated = Some(inputs);
+            }
+            self.response.ui(ui);
+            self.effort.ui(ui);
+        });
+    }
+    fn draw_config(&mut self, ui: &mut Ui) {
+        let config = &mut self.config;
+        ui.horizontal(|ui| {
+            ui.label("Plant:");
+            let first_order = Plant::FirstOrder {
+                gain: 1.0,
+                tau: 0.5,
+            };
+            let second_order = Plant::SecondOrder {
+                gain: 1.0,
+                wn: 5.0,
+                zeta: 0.3,
+            };
+            let drivetrain = Plant::Drivetrain(Drivetrain::default());
+            // switching model resets its parameters to the defaults
+            for (model, label) in [
+                (first_order, "First order"),
+                (second_order, "Second order"),
+                (drivetrain, "Drivetrain"),
+            ] {
+                let selected =
+                    std::mem::discriminant(&config.plant) == std::mem::discriminant(&model);
+                if ui.selectable_label(selected, label).clicked() && !selected {
+                    config.plant = model;
+                }
+            }
+        });
+
+        egui::Grid::new("simulation plant")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let mut row = |label: &str, value: &mut f64, speed: f64| {
+                    ui.label(label);
+                    ui.add(egui::DragValue::new(value).speed(speed));
+                    ui.end_row();
+                };
+                match &mut config.plant {
+                    Plant::FirstOrder { gain, tau } => {
+                        row("gain", gain, 0.01);
+                        row("time constant (s)", tau, 0.01);
+                        *tau = tau.max(1e-3);
+                    }
+                    Plant::SecondOrder { gain, wn, zeta } => {
+                        row("gain", gain, 0.01);
+                        row("natural frequency (rad/s)", wn, 0.1);
+                        row("damping ratio", zeta, 0.01);
+                        *wn = wn.max(1e-3);
+                        *zeta = zeta.max(0.0);
+                    }
+                    Plant::Drivetrain(d) => {
+                        row("mass (kg)", &mut d.mass, 0.1);
+                        row("wheel radius (m)", &mut d.wheel_radius, 0.001);
+                        row("gear ratio", &mut d.gear_ratio, 0.01);
+                        row("motors", &mut d.motors, 1.0);
+                        row("torque constant (N m/A)", &mut d.kt, 0.0001);
+                        row("speed constant (rad/s/V)", &mut d.kv, 0.1);
+                        row("resistance (ohm)", &mut d.resistance, 0.01);
+                        d.mass = d.mass.max(0.1);
+                        d.wheel_radius = d.wheel_radius.max(1e-3);
+                        d.gear_ratio = d.gear_ratio.max(1e-3);
+                        d.motors = d.motors.round().max(1.0);
+                        d.kv = d.kv.max(1e-3);
+                        d.resistance = d.resistance.max(1e-3);
+                    }
+                }
+            });
+
+        ui.horizontal(|ui| {
+            let setpoint = &mut config.setpoint;
+            let (mut is_step, mut value) = match *setpoint {
+                Setpoint::Step(v) => (true, v),
+                Setpoint::Ramp(v) => (false, v),
+            };
+            ui.selectable_value(&mut is_step, true, "Step");
+            ui.selectable_value(&mut is_step, false, "Ramp");
+            ui.label(if is_step { "to" } else { "at (units/s)" });
+            ui.add(egui::DragValue::new(&mut value).speed(0.01));
+            *setpoint = if is_step {
+                Setpoint::Step(value)
+            } else {
+                Setpoint::Ramp(value)
+            };
+        });
+        ui.horizontal(|ui| {
+            ui.label("Duration (s):");
+            ui.add(
+                egui::DragValue::new(&mut config.duration)
+                    .speed(0.1)
+                    .clamp_range(0.1..=60.0),
+            );
+            ui.label("Period (s):");
+            ui.add(
+                egui::DragValue::new(&mut config.dt)
+                    .speed(0.001)
+                    .clamp_range(0.001..=0.05),
+            );
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulate(plant: Plant, gains: &[(&str, f64)]) -> Simulation {
+        let mut simulation = Simulation::default();
+        simulation.config.plant = plant;
+        let gains: Vec<(String, f64)> = gains
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), *value))
+            .collect();
+        simulation.run(&gains);
+        simulation
+    }
+
+    #[test]
+    fn proportional_control_of_a_first_order_plant() {
+        let (gain, kp) = (2.0, 3.0);
+        let plant = Plant::FirstOrder { gain, tau: 0.5 };
+        let simulation = simulate(plant, &[("kp", kp)]);
+
+        let output = simulation.response.points("output").unwrap();
+        let [t, y] = *output.last().unwrap();
+        assert!((t - 5.0).abs() < 1e-9);
+        // kp alone leaves a steady state error, settling at kp gain / (1 + kp gain)
+        let settled = kp * gain / (1.0 + kp * gain);
+        assert!((y - settled).abs() < 1e-6);
+        // with no overshoot
+        assert!(output.iter().all(|[_, y]| *y <= settled + 1e-9));
+        let effort = simulation.effort.points("effort").unwrap();
+        assert_eq!(effort[0], [0.0, kp]);
+    }
+
+    #[test]
+    fn output_clamp_bounds_the_effort() {
+        let plant = Plant::FirstOrder {
+            gain: 1.0,
+            tau: 0.5,
+        };
+        let simulation = simulate(plant, &[("kp", 100.0), ("out_clamp", 0.5)]);
+
+        let effort = simulation.effort.points("effort").unwrap();
+        assert!(effort.iter().all(|[_, u]| u.abs() <= 0.5));
+        assert_eq!(effort[0][1], 0.5);
+        // the clamped effort can only bring the plant to half the setpoint
+        let [_, y] = *simulation
+            .response
+            .points("output")
+            .unwrap()
+            .last()
+            .unwrap();
+        assert!(y < 0.5 + 1e-9);
+        assert!(y > 0.45);
+    }
+
+    #[test]
+    fn integral_removes_the_steady_state_error() {
+        let plant = Plant::FirstOrder {
+            gain: 2.0,
+            tau: 0.5,
+        };
+        let simulation = simulate(plant, &[("kp", 3.0), ("ki", 5.0)]);
+        let [_, y] = *simulation
+            .response
+            .points("output")
+            .unwrap()
+            .last()
+            .unwrap();
+        assert!((y - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,72 @@
+use eframe::egui;
+use egui::Ui;
+
+use crate::{
+    drive_sim::{self, Drivetrain, Executor, Sample, MAX_DURATION},
+    routine::Action,
+};
+
+// the planned path parametrised by how long the simulated drivetrain takes to
+// follow it
+#[derive(Default)]
+pub struct Timeline {
+    // what the samples were simulated from
+    actions: Vec<Action>,
+    drivetrain: Drivetrain,
+    samples: Vec<Sample>,
+    // scrubbed position in seconds
+    time: f64,
+}
+
+impl Timeline {
+    // resimulates if the routine or drivetrain has changed, returns true if it did
+    pub fn set_routine(&mut self, actions: &[Action], drivetrain: Drivetrain) -> bool {
+        if !self.samples.is_empty() && self.actions == actions && self.drivetrain == drivetrain {
+            return false;
+        }
+        self.actions = actions.to_vec();
+        self.drivetrain = drivetrain;
+        self.samples.clear();
+        if actions.is_empty() {
+            return true;
+        }
+        let mut executor = Executor::new(drivetrain, actions);
+        self.samples.push(executor.sample());
+        while !executor.finished() && executor.time < MAX_DURATION {
+            executor.step(drive_sim::DT);
+            self.samples.push(executor.sample());
+        }
+        true
+    }
+    fn duration(&self) -> f64 {
+        self.samples.last().map_or(0.0, |sample| sample.time)
+    }
+    // the drivetrain at the scrubbed time
+    pub fn current(&self) -> Option<&Sample> {
+        let i = self
+            .samples
+            .partition_point(|sample| sample.time < self.time);
+        self.samples.get(i).or(self.samples.last())
+    }
+    // width and length of the robot following the path
+    pub fn size(&self) -> [f64; 2] {
+        self.drivetrain.size
+    }
+    pub fn ui(&mut self, ui: &mut Ui) {
+        if self.samples.is_empty() {
+            ui.weak("The routine is empty");
+            return;
+        }
+        let duration = self.duration();
+        self.time = self.time.min(duration);
+        ui.spacing_mut().slider_width = (ui.available_width() - 80.0).max(100.0);
+        ui.add(
+            egui::Slider::new(&mut self.time, 0.0..=duration)
+                .suffix(" s")
+                .max_decimals(2),
+        );
+        if let Some(sample) = self.current() {
+            ui.label(sample.describe(&self.actions));
+        }
+    }
+}
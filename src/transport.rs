@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+use communication::packets::{ToClient, ToRobot};
+
+use crate::{
+    connection::Connection, graph, inspector::Inspector, logging::Logging, mock::Mock, pid::Pid,
+    plot::Plot, recorder::Recorder, replay::Replay,
+};
+
+// a source of packets from the robot and a sink for packets to it
+pub trait Transport {
+    fn get_packets(&mut self) -> Vec<ToClient>;
+    fn send_packet(&mut self, packet: ToRobot);
+    // true when everything received so far has to be forgotten, e.g. after
+    // seeking backwards in a replay
+    fn take_reset(&mut self) -> bool {
+        false
+    }
+}
+
+impl Transport for Connection {
+    fn get_packets(&mut self) -> Vec<ToClient> {
+        Connection::get_packets(self)
+    }
+    fn send_packet(&mut self, packet: ToRobot) {
+        Connection::send_packet(self, packet)
+    }
+}
+
+impl Transport for Replay {
+    fn get_packets(&mut self) -> Vec<ToClient> {
+        Replay::get_packets(self)
+    }
+    fn send_packet(&mut self, _: ToRobot) {
+        log::warn!("Replaying a recording, dropping packet for the robot");
+    }
+    fn take_reset(&mut self) -> bool {
+        Replay::take_reset(self)
+    }
+}
+
+impl Transport for Mock {
+    fn get_packets(&mut self) -> Vec<ToClient> {
+        Mock::get_packets(self)
+    }
+    fn send_packet(&mut self, packet: ToRobot) {
+        Mock::send_packet(self, packet)
+    }
+    fn take_reset(&mut self) -> bool {
+        Mock::take_reset(self)
+    }
+}
+
+// in memory transport for driving the editor without a robot
+#[derive(Default)]
+pub struct Fake {
+    // each call to get_packets returns the next batch
+    pub incoming: VecDeque<Vec<ToClient>>,
+    pub sent: Vec<ToRobot>,
+    pub reset: bool,
+}
+
+impl Fake {
+    pub fn push(&mut self, pkts: Vec<ToClient>) {
+        self.incoming.push_back(pkts);
+    }
+}
+
+impl Transport for Fake {
+    fn get_packets(&mut self) -> Vec<ToClient> {
+        self.incoming.pop_front().unwrap_or_default()
+    }
+    fn send_packet(&mut self, packet: ToRobot) {
+        self.sent.push(packet);
+    }
+    fn take_reset(&mut self) -> bool {
+        std::mem::take(&mut self.reset)
+    }
+}
+
+// everything that is updated by packets from the robot
+pub struct Handlers<'a> {
+    pub plot: &'a mut Plot,
+    pub logging: &'a mut Logging,
+    pub graphing: &'a mut graph::Manager,
+    pub pid: &'a mut Pid,
+    // received packets are recorded when set
+    pub recorder: Option<&'a mut Recorder>,
+    pub inspector: Option<&'a mut Inspector>,
+}
+
+impl Handlers<'_> {
+    pub fn receive(&mut self, transport: &mut dyn Transport) {
+        if transport.take_reset() {
+            self.clear();
+        }
+        let pkts = transport.get_packets();
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&pkts);
+        }
+        self.handle(pkts);
+    }
+    pub fn handle(&mut self, pkts: Vec<ToClient>) {
+        if let Some(inspector) = &mut self.inspector {
+            inspector.record(&pkts);
+        }
+        let mut logs = Vec::new();
+        let mut point_buffers = Vec::new();
+        for pkt in pkts {
+            match pkt {
+                ToClient::Log(l) => logs.push(l),
+                ToClient::PointBuffer(plt_name, subplt_name, buffer) => {
+                    self.pid.add_buffer(&plt_name, &subplt_name, &buffer);
+                    point_buffers.push((plt_name, subplt_name, buffer));
+                }
+                ToClient::Odometry(name, dim, pos, rot) => {
+                    self.plot.set_odom(name, dim, pos, rot);
+                }
+                other => {
+                    if let Some(inspector) = &mut self.inspector {
+                        inspector.record_unhandled(&other);
+                    }
+                }
+            }
+        }
+
+        self.logging.add_logs(logs);
+        self.graphing.add_buffers(point_buffers);
+        if self.graphing.take_restarted() {
+            self.logging.mark_restart();
+        }
+    }
+    // forget everything received, used when the packet source changes
+    pub fn clear(&mut self) {
+        self.logging.clear();
+        *self.graphing = graph::Manager::default();
+        self.plot.clear_odom();
+    }
+}
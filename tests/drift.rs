@@ -0,0 +1,78 @@
+use std::f64::consts::PI;
+
+use path_gui_editor::{
+    drift::{self, Noise},
+    drive_sim::Drivetrain,
+    routine::{self, Action},
+};
+
+fn routine() -> Vec<Action> {
+    vec![
+        Action::StartAt {
+            pos: [0.0, -1.5],
+            heading: 0.0,
+        },
+        Action::MoveRel { rel: 0.5 },
+        Action::TurnRel { angle: PI / 2.0 },
+        Action::MoveRel { rel: 1.0 },
+        Action::StartAt {
+            pos: [1.0, 1.0],
+            heading: PI,
+        },
+        Action::MoveTo { pos: [1.0, 0.0] },
+    ]
+}
+
+#[test]
+fn no_noise_follows_the_plan() {
+    let actions = routine();
+    let noise = Noise {
+        slip: 0.0,
+        heading: 0.0,
+        turn: 0.0,
+    };
+    let spreads = drift::simulate(&actions, Drivetrain::default(), noise, 20);
+    let planned = routine::plan(&actions);
+    assert_eq!(spreads.len(), actions.len());
+    for (spread, planned) in spreads.iter().zip(&planned) {
+        assert_eq!(spread.positions.len(), 20);
+        assert!((spread.mean - planned.pos).mag() < 1e-6);
+        let ([major, _], _) = spread.axes();
+        assert!(major < 1e-6);
+        assert!(spread.heading < 1e-6);
+    }
+}
+
+#[test]
+fn spread_grows_until_the_robot_is_placed_again() {
+    let actions = routine();
+    let spreads = drift::simulate(&actions, Drivetrain::default(), Noise::default(), 500);
+    let major = |i: usize| spreads[i].axes().0[0];
+    assert!(major(0) < 1e-9);
+    assert!(major(1) > 0.0);
+    assert!(major(3) > major(1));
+    // placing the robot again resets what it has drifted
+    assert!(major(4) < 1e-9);
+    assert!(major(5) > 0.0);
+
+    // slipping on the first drive spreads the robot along it, which is +y
+    let [xx, _, yy] = spreads[1].cov;
+    assert!(yy > xx);
+}
+
+#[test]
+fn the_last_action_is_included() {
+    let actions = vec![
+        Action::StartAt {
+            pos: [0.0, 0.0],
+            heading: 0.0,
+        },
+        Action::MoveRel { rel: 1.0 },
+    ];
+    let spreads = drift::simulate(&actions, Drivetrain::default(), Noise::default(), 100);
+    assert_eq!(spreads.len(), 2);
+    assert_eq!(spreads[1].action, 1);
+    assert!(spreads[1].axes().0[0] > 0.0);
+    // forward is +y with no heading
+    assert!((spreads[1].mean.y() - 1.0).abs() < 0.05);
+}
@@ -0,0 +1,59 @@
+use std::f64::consts::PI;
+
+use path_gui_editor::{
+    drive_sim::{Drivetrain, Executor},
+    routine::{self, wrap_angle, Action},
+};
+
+fn routine() -> Vec<Action> {
+    vec![
+        Action::StartAt {
+            pos: [0.0, -1.7],
+            heading: 0.0,
+        },
+        Action::MoveRelAbs { rel: 0.2 },
+        Action::MoveRel { rel: 1.0 },
+        Action::TurnTo { heading: PI / 2.0 },
+        Action::MoveTo { pos: [-1.0, 0.0] },
+        Action::MoveRel { rel: -0.5 },
+        Action::TurnRel { angle: -3.0 * PI },
+    ]
+}
+
+fn run(executor: &mut Executor, mut each: impl FnMut(&Executor)) {
+    while !executor.finished() {
+        executor.step(0.005);
+        each(executor);
+        assert!(executor.time < 60.0, "routine never finished");
+    }
+}
+
+#[test]
+fn ends_at_the_planned_pose() {
+    let actions = routine();
+    let mut executor = Executor::new(Drivetrain::default(), &actions);
+    run(&mut executor, |_| {});
+
+    let planned = *routine::plan(&actions).last().unwrap();
+    assert!((executor.pose.pos - planned.pos).mag() < 1e-6);
+    assert!(wrap_angle(executor.pose.heading - planned.heading).abs() < 1e-6);
+    assert_eq!(executor.action, actions.len());
+}
+
+#[test]
+fn stays_within_the_drivetrain_limits() {
+    let drivetrain = Drivetrain::default();
+    let half_track = 0.5 * drivetrain.track_width;
+    let mut executor = Executor::new(drivetrain, &routine());
+    let mut last_speed = 0.0f64;
+    run(&mut executor, |executor| {
+        let wheel_speed = executor
+            .velocity
+            .abs()
+            .max(executor.angular.abs() * half_track);
+        assert!(wheel_speed <= drivetrain.max_speed + 1e-9);
+        // segments stop dead between each other, so only check speeding up
+        assert!(wheel_speed - last_speed <= drivetrain.max_accel * 0.005 + 1e-9);
+        last_speed = wheel_speed;
+    });
+}
@@ -0,0 +1,157 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use communication::packets::{Log, ToClient};
+use path_gui_editor::session_file::{self, Metadata, SessionWriter};
+
+// a directory of its own for each test so they can run in parallel
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("path_gui_editor-{test}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn metadata() -> Metadata {
+    Metadata {
+        robot: "test".to_owned(),
+        notes: "notes".to_owned(),
+        started: 946_684_800_000,
+        ..Default::default()
+    }
+}
+
+fn message(msg: &str) -> ToClient {
+    ToClient::Log(Log {
+        level: log::Level::Info,
+        msg: msg.to_owned(),
+        timestamp: SystemTime::UNIX_EPOCH,
+    })
+}
+
+// records "0", "1", ... 10ms apart and closes the file
+fn record(dir: &Path, count: usize) -> PathBuf {
+    let metadata = metadata();
+    let mut writer = SessionWriter::create(dir, &metadata).unwrap();
+    for i in 0..count {
+        let received = metadata.start_time() + Duration::from_millis(10 * i as u64);
+        writer.write(received, &message(&i.to_string())).unwrap();
+    }
+    writer.path().to_owned()
+}
+
+fn messages(path: &Path) -> Vec<String> {
+    let index = session_file::read_index(path).unwrap();
+    let mut reader = BufReader::new(File::open(path).unwrap());
+    index
+        .records
+        .iter()
+        .map(
+            |&(_, offset)| match session_file::read_record(&mut reader, offset).unwrap() {
+                ToClient::Log(log) => log.msg,
+                _ => panic!("expected a log"),
+            },
+        )
+        .collect()
+}
+
+#[test]
+fn packets_are_read_back() {
+    let dir = temp_dir("read_back");
+    let path = record(&dir, 3);
+    assert!(path.starts_with(&dir));
+
+    let index = session_file::read_index(&path).unwrap();
+    assert_eq!(index.metadata.robot, "test");
+    assert_eq!(index.metadata.notes, "notes");
+    assert_eq!(index.metadata.start_time(), metadata().start_time());
+    let times: Vec<u64> = index.records.iter().map(|&(micros, _)| micros).collect();
+    assert_eq!(times, [0, 10_000, 20_000]);
+    assert_eq!(messages(&path), ["0", "1", "2"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn closing_writes_the_footer() {
+    let dir = temp_dir("footer");
+    let path = record(&dir, 3);
+    let index = session_file::read_index(&path).unwrap();
+
+    // a (time, offset) pair per record, the footer offset and the magic
+    let len = fs::metadata(&path).unwrap().len();
+    assert_eq!(len, index.end + 3 * 16 + 16);
+    let offsets: Vec<u64> = index.records.iter().map(|&(_, offset)| offset).collect();
+    assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(offsets[2] < index.end);
+
+    // the footer has the same index scanning the records would give
+    let file = OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_len(index.end).unwrap();
+    drop(file);
+    let scanned = session_file::read_index(&path).unwrap();
+    assert_eq!(scanned.records, index.records);
+    assert_eq!(scanned.end, index.end);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn truncated_recordings_keep_complete_records() {
+    let dir = temp_dir("truncated");
+    let path = record(&dir, 3);
+    let index = session_file::read_index(&path).unwrap();
+
+    // cut off the footer and half of the last record
+    let last = index.records[2].1;
+    let file = OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_len((last + index.end) / 2).unwrap();
+    drop(file);
+
+    let recovered = session_file::read_index(&path).unwrap();
+    assert_eq!(recovered.records, index.records[..2]);
+    assert_eq!(recovered.end, last);
+    assert_eq!(messages(&path), ["0", "1"]);
+
+    // appending carries on after the last complete record
+    let mut writer = SessionWriter::append(&path).unwrap();
+    writer
+        .write(
+            metadata().start_time() + Duration::from_millis(50),
+            &message("appended"),
+        )
+        .unwrap();
+    drop(writer);
+    let appended = session_file::read_index(&path).unwrap();
+    assert_eq!(appended.records.len(), 3);
+    assert_eq!(appended.records[2], (50_000, last));
+    assert_eq!(messages(&path), ["0", "1", "appended"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn recordings_started_together_are_kept() {
+    let dir = temp_dir("same_start");
+    let first = record(&dir, 1);
+    let second = record(&dir, 2);
+    assert_ne!(first, second);
+    assert_eq!(messages(&first), ["0"]);
+    assert_eq!(messages(&second), ["0", "1"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn other_files_are_rejected() {
+    let dir = temp_dir("rejected");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("not.session");
+    fs::write(&path, b"not a recording at all").unwrap();
+    assert!(session_file::read_index(&path).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,354 @@
+use std::time::{Duration, SystemTime};
+
+use communication::{
+    packets::{Log, ToClient, ToRobot},
+    Buffer,
+};
+use eframe::egui;
+use path_gui_editor::{
+    graph,
+    inspector::Inspector,
+    logging::{Logging, Source},
+    mock::Mock,
+    pid::Pid,
+    plot::Plot,
+    replay::Replay,
+    session_file::{Metadata, SessionWriter},
+    transport::{Fake, Handlers, Transport},
+};
+
+// the parts of the app that packets end up in
+struct Editor {
+    plot: Plot,
+    logging: Logging,
+    graphing: graph::Manager,
+    pid: Pid,
+    inspector: Inspector,
+}
+
+impl Editor {
+    fn new() -> Self {
+        let mut logging = Logging::load(None);
+        // keeps the tests from writing log files
+        logging.set_replaying(true);
+        Self {
+            plot: Plot::new(&egui::Context::default()),
+            logging,
+            graphing: graph::Manager::default(),
+            pid: Pid::load(None),
+            inspector: Inspector::default(),
+        }
+    }
+    fn receive(&mut self, transport: &mut dyn Transport) {
+        Handlers {
+            plot: &mut self.plot,
+            logging: &mut self.logging,
+            graphing: &mut self.graphing,
+            pid: &mut self.pid,
+            recorder: None,
+            inspector: Some(&mut self.inspector),
+        }
+        .receive(transport);
+    }
+    fn messages(&self) -> Vec<&str> {
+        self.logging
+            .entries()
+            .iter()
+            .map(|entry| entry.msg.as_str())
+            .collect()
+    }
+}
+
+fn message(msg: &str, secs: u64) -> ToClient {
+    ToClient::Log(Log {
+        level: log::Level::Info,
+        msg: msg.to_owned(),
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+    })
+}
+
+fn scalar(plot: &str, subplot: &str, points: &[(f64, f64)]) -> ToClient {
+    ToClient::PointBuffer(
+        plot.to_owned(),
+        subplot.to_owned(),
+        Buffer::Scalar(
+            points
+                .iter()
+                .map(|&(t, v)| (Duration::from_secs_f64(t), v))
+                .collect(),
+        ),
+    )
+}
+
+fn odometry(name: &str, pos: [f64; 2], heading: f64) -> ToClient {
+    ToClient::Odometry(name.to_owned(), [0.4, 0.4], pos, heading)
+}
+
+#[test]
+fn logs_are_shown() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![message("starting", 1), message("driving", 2)]);
+    editor.receive(&mut fake);
+
+    assert_eq!(editor.messages(), ["starting", "driving"]);
+    let entry = &editor.logging.entries()[0];
+    assert_eq!(entry.level, log::Level::Info);
+    assert_eq!(entry.source, Source::Robot);
+}
+
+#[test]
+fn repeated_logs_are_collapsed() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![message("stalled", 1), message("stalled", 2)]);
+    fake.push(vec![message("stalled", 3), message("moving", 4)]);
+    editor.receive(&mut fake);
+    editor.receive(&mut fake);
+
+    assert_eq!(editor.messages(), ["stalled", "moving"]);
+    assert_eq!(editor.logging.entries()[0].repeats, 3);
+}
+
+#[test]
+fn empty_batches_change_nothing() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    editor.receive(&mut fake);
+
+    assert!(editor.logging.entries().is_empty());
+    assert!(editor.graphing.series().is_empty());
+}
+
+#[test]
+fn scalar_buffers_are_graphed() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![scalar(
+        "drive",
+        "velocity",
+        &[(0.0, 0.0), (0.01, 0.5)],
+    )]);
+    fake.push(vec![scalar("drive", "velocity", &[(0.02, 1.0)])]);
+    editor.receive(&mut fake);
+    editor.receive(&mut fake);
+
+    assert_eq!(
+        editor.graphing.points("drive", "velocity"),
+        Some(&[[0.0, 0.0], [0.01, 0.5], [0.02, 1.0]][..])
+    );
+}
+
+#[test]
+fn vector_buffers_are_split_into_components() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![
+        ToClient::PointBuffer(
+            "odom".to_owned(),
+            "position".to_owned(),
+            Buffer::Vec2(vec![(Duration::ZERO, [1.0, 2.0])]),
+        ),
+        ToClient::PointBuffer(
+            "accel".to_owned(),
+            "accel".to_owned(),
+            Buffer::Vec3(vec![(Duration::ZERO, [3.0, 4.0, 5.0])]),
+        ),
+    ]);
+    editor.receive(&mut fake);
+
+    let series = [
+        ("accel", "x"),
+        ("accel", "y"),
+        ("accel", "z"),
+        ("odom", "position (x)"),
+        ("odom", "position (y)"),
+    ]
+    .map(|(graph, subplot)| (graph.to_owned(), subplot.to_owned()));
+    assert_eq!(editor.graphing.series(), series);
+    assert_eq!(
+        editor.graphing.points("odom", "position (y)"),
+        Some(&[[0.0, 2.0]][..])
+    );
+    assert_eq!(
+        editor.graphing.points("accel", "z"),
+        Some(&[[0.0, 5.0]][..])
+    );
+}
+
+#[test]
+fn odometry_moves_the_robot() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![odometry("robot", [0.0, 0.0], 0.0)]);
+    fake.push(vec![odometry("robot", [0.5, -1.0], 1.5)]);
+    editor.receive(&mut fake);
+    editor.receive(&mut fake);
+
+    assert_eq!(
+        editor.plot.odom("robot"),
+        Some(([0.4, 0.4], [0.5, -1.0], 1.5))
+    );
+    assert_eq!(editor.plot.odom("other"), None);
+}
+
+#[test]
+fn unhandled_packets_are_counted() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![
+        message("handled", 1),
+        scalar("drive", "velocity", &[(0.0, 0.0)]),
+        odometry("robot", [0.0, 0.0], 0.0),
+    ]);
+    editor.receive(&mut fake);
+    assert_eq!(editor.inspector.unhandled(), 0);
+
+    // every packet the pinned protocol has is handled, so stand in for one
+    // the editor doesn't know about
+    editor.inspector.record_unhandled(&message("ignored", 2));
+    editor.inspector.record_unhandled(&message("ignored", 3));
+    assert_eq!(editor.inspector.unhandled(), 2);
+}
+
+#[test]
+fn mock_scripts_play_in_order() {
+    let mut editor = Editor::new();
+    let mut mock = Mock::scripted(
+        "test",
+        "# out of order on purpose\n\
+         0.2 odom robot 1 2 0.5\n\
+         0 log warn starting up\n\
+         0.1 scalar drive velocity 1.5\n",
+    )
+    .unwrap();
+    let mut fake = Fake::default();
+    fake.push(mock.advance(Duration::from_millis(150)));
+    editor.receive(&mut fake);
+
+    assert_eq!(editor.messages(), ["starting up"]);
+    assert_eq!(editor.logging.entries()[0].level, log::Level::Warn);
+    assert_eq!(
+        editor.graphing.points("drive", "velocity"),
+        Some(&[[0.1, 1.5]][..])
+    );
+    assert_eq!(editor.plot.odom("robot"), None);
+
+    fake.push(mock.advance(Duration::from_millis(250)));
+    editor.receive(&mut fake);
+    assert_eq!(
+        editor.plot.odom("robot"),
+        Some(([0.45, 0.45], [1.0, 2.0], 0.5))
+    );
+}
+
+#[test]
+fn mock_reports_the_gains_it_is_sent() {
+    let mut editor = Editor::new();
+    let mut mock = Mock::random(1);
+    let mut fake = Fake::default();
+    fake.push(mock.advance(Duration::ZERO));
+    mock.send_packet(ToRobot::Pid((2.0, 0.5, 0.1)));
+    fake.push(mock.advance(Duration::from_millis(20)));
+    editor.receive(&mut fake);
+    editor.receive(&mut fake);
+
+    assert_eq!(
+        editor.graphing.points("pid", "kp"),
+        Some(&[[0.0, 1.0], [0.02, 2.0]][..])
+    );
+    assert!(editor
+        .messages()
+        .contains(&"Gains set to kp 2 ki 0.5 kd 0.1"));
+}
+
+#[test]
+fn graph_time_going_backwards_marks_a_restart() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![
+        message("before", 1),
+        scalar("drive", "velocity", &[(1.0, 0.0), (1.05, 0.1)]),
+    ]);
+    fake.push(vec![scalar("drive", "velocity", &[(0.0, 0.0)])]);
+    fake.push(vec![message("after", 2)]);
+    for _ in 0..3 {
+        editor.receive(&mut fake);
+    }
+
+    assert_eq!(editor.messages(), ["before", "after"]);
+    let since_restart: Vec<_> = editor
+        .logging
+        .since_restart()
+        .iter()
+        .map(|entry| entry.msg.as_str())
+        .collect();
+    assert_eq!(since_restart, ["after"]);
+    assert_eq!(
+        editor.graphing.points("drive", "velocity"),
+        Some(&[[0.0, 0.0]][..])
+    );
+}
+
+#[test]
+fn reset_forgets_everything() {
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![
+        message("old", 1),
+        scalar("drive", "velocity", &[(0.0, 1.0)]),
+        odometry("robot", [1.0, 1.0], 0.0),
+    ]);
+    editor.receive(&mut fake);
+
+    fake.reset = true;
+    fake.push(vec![message("new", 1)]);
+    editor.receive(&mut fake);
+
+    assert_eq!(editor.messages(), ["new"]);
+    assert!(editor.graphing.series().is_empty());
+    assert_eq!(editor.plot.odom("robot"), None);
+}
+
+#[test]
+fn replay_delivers_recorded_packets() {
+    let metadata = Metadata {
+        robot: "test".to_owned(),
+        started: 946_684_800_000,
+        ..Default::default()
+    };
+    // somewhere of its own so it doesn't touch real recordings
+    let dir = std::env::temp_dir().join(format!("path_gui_editor-replay-{}", std::process::id()));
+    let mut writer = SessionWriter::create(&dir, &metadata).unwrap();
+    let path = writer.path().to_owned();
+    for pkt in [
+        message("recorded", 1),
+        scalar("drive", "velocity", &[(0.0, 2.0)]),
+        odometry("robot", [0.2, 0.3], 0.5),
+    ] {
+        writer.write(metadata.start_time(), &pkt).unwrap();
+    }
+    drop(writer);
+
+    let mut editor = Editor::new();
+    let mut fake = Fake::default();
+    fake.push(vec![message("live", 1)]);
+    editor.receive(&mut fake);
+
+    let mut replay = Replay::open(&path).unwrap();
+    editor.receive(&mut replay);
+
+    // opening a replay forgets the live packets
+    assert_eq!(editor.messages(), ["recorded"]);
+    assert_eq!(
+        editor.graphing.points("drive", "velocity"),
+        Some(&[[0.0, 2.0]][..])
+    );
+    assert_eq!(
+        editor.plot.odom("robot"),
+        Some(([0.4, 0.4], [0.2, 0.3], 0.5))
+    );
+
+    drop(replay);
+    std::fs::remove_dir_all(&dir).unwrap();
+}